@@ -0,0 +1,120 @@
+//! Size budgets.
+//!
+//! Checked after the report is printed, so a failing CI job still shows what has grown.
+
+use cargo_bloat::CrateData;
+
+use crate::print::format_size;
+use crate::Args;
+
+#[derive(Clone, Default)]
+pub struct Budget {
+    pub file_size: Option<u64>,
+    pub text_size: Option<u64>,
+    pub crates: Vec<(String, u64)>,
+}
+
+impl Budget {
+    pub fn is_empty(&self) -> bool {
+        self.file_size.is_none() && self.text_size.is_none() && self.crates.is_empty()
+    }
+}
+
+/// Parses a size in bytes, like `512`, `200KiB` or `1.5MiB`.
+pub fn parse_size(s: &str) -> Result<u64, &'static str> {
+    let units = [
+        ("GiB", 1 << 30),
+        ("MiB", 1 << 20),
+        ("KiB", 1 << 10),
+        ("B", 1),
+    ];
+    let (number, unit) = units
+        .iter()
+        .find_map(|&(suffix, unit)| s.strip_suffix(suffix).map(|n| (n, unit)))
+        .unwrap_or((s, 1));
+
+    match number.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok((n * unit as f64) as u64),
+        _ => Err("invalid size"),
+    }
+}
+
+/// Parses a crate budget, like `regex=200KiB`.
+pub fn parse_crate_size(s: &str) -> Result<(String, u64), &'static str> {
+    let mut parts = s.splitn(2, '=');
+    let name = parts.next().unwrap_or("").trim();
+    let size = parts.next().ok_or("expected CRATE=SIZE")?;
+    if name.is_empty() {
+        return Err("expected CRATE=SIZE");
+    }
+
+    Ok((name.to_string(), parse_size(size)?))
+}
+
+/// Returns a message for each exceeded budget.
+///
+/// Warns about budgeted crates that are not found in any binary, which is usually a typo.
+pub fn check(crates: &[CrateData], args: &Args) -> Vec<String> {
+    let budget = &args.budget;
+    let mut messages = Vec::new();
+    if budget.is_empty() {
+        return messages;
+    }
+
+    let mut missing: Vec<&str> = budget
+        .crates
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    for crate_data in crates {
+        let path = crate_data.exe_path.as_deref().unwrap_or("the binary");
+
+        let sizes = [
+            ("file size", crate_data.data.file_size, budget.file_size),
+            (".text size", crate_data.data.text_size, budget.text_size),
+        ];
+        for &(name, size, max) in sizes.iter() {
+            if let Some(max) = max {
+                if size > max {
+                    messages.push(format!(
+                        "'{}' {} is {}, which exceeds the budget of {}",
+                        path,
+                        name,
+                        format_size(size),
+                        format_size(max)
+                    ));
+                }
+            }
+        }
+
+        if budget.crates.is_empty() {
+            continue;
+        }
+
+        let crate_sizes = cargo_bloat::crate_sizes(crate_data, &args.options);
+        missing.retain(|name| !crate_sizes.contains_key(*name));
+        for (name, max) in &budget.crates {
+            if let Some(&size) = crate_sizes.get(name) {
+                if size > *max {
+                    messages.push(format!(
+                        "the '{}' crate in '{}' is {}, which exceeds the budget of {}",
+                        name,
+                        path,
+                        format_size(size),
+                        format_size(*max)
+                    ));
+                }
+            }
+        }
+    }
+
+    for name in missing {
+        eprintln!(
+            "Warning: the '{}' crate has a budget, but it was not found.",
+            name
+        );
+    }
+
+    messages
+}
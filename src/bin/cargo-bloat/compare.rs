@@ -0,0 +1,307 @@
+//! Comparison against a git revision.
+//!
+//! The revision is checked out into a temporary worktree and built
+//! with the same arguments into a separate target directory.
+
+use std::collections::HashMap;
+use std::process::Command;
+use std::{fs, path};
+
+use cargo_bloat::{
+    bloat_dir, build, cargo_command, crate_sizes, diff_sizes, CrateData, Error, Options,
+};
+
+use crate::print::{format_size, format_size_diff, print_warnings};
+use crate::table::Table;
+use crate::{Args, MessageFormat};
+
+/// Sizes of a single binary.
+pub(crate) struct Entry {
+    path: String,
+    /// A file name, which is used to match binaries, since trees can have different target dirs.
+    name: String,
+    pub(crate) file_size: u64,
+    pub(crate) text_size: u64,
+    crates: HashMap<String, u64>,
+    symbols: HashMap<String, u64>,
+}
+
+impl Entry {
+    pub(crate) fn new(d: &CrateData, args: &Args) -> Self {
+        let path = d.exe_path.clone().unwrap_or_default();
+        let name = path::Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone());
+
+        let mut symbols = HashMap::new();
+        for sym in &d.data.symbols {
+            *symbols.entry(sym.name.trimmed.clone()).or_insert(0) += sym.size;
+        }
+
+        Entry {
+            path,
+            name,
+            file_size: d.data.file_size,
+            text_size: d.data.text_size,
+            crates: crate_sizes(d, &args.options),
+            symbols,
+        }
+    }
+}
+
+pub(crate) struct Comparison {
+    pub(crate) name: String,
+    pub(crate) base: Option<Entry>,
+    pub(crate) head: Option<Entry>,
+}
+
+/// Builds the working tree and the `rev` revision and prints the difference.
+pub fn run(args: &Args, workspace_root: &str, rev: &str) -> Result<(), Error> {
+    let toplevel = git(workspace_root, &["rev-parse", "--show-toplevel"])?;
+    let commit = git(
+        workspace_root,
+        &["rev-parse", "--verify", &format!("{}^{{commit}}", rev)],
+    )
+    .map_err(|_| Error::GitError(format!("unknown revision '{}'", rev)))?;
+    let manifest_path = locate_manifest(&args.options)?;
+
+    eprintln!("     Building the working tree");
+    let head = build(&args.options, workspace_root)?;
+    print_warnings(&head);
+
+    let bloat_dir = bloat_dir(&args.options, workspace_root)?;
+    let worktree = bloat_dir.join("compare-worktree");
+    if worktree.exists() {
+        // A leftover from an interrupted run.
+        let _ = git(
+            &toplevel,
+            &["worktree", "remove", "--force", &path_str(&worktree)?],
+        );
+        let _ = fs::remove_dir_all(&worktree);
+    }
+
+    eprintln!(
+        "     Building {} ({})",
+        rev,
+        &commit[..commit.len().min(12)]
+    );
+    git(
+        &toplevel,
+        &[
+            "worktree",
+            "add",
+            "--detach",
+            &path_str(&worktree)?,
+            &commit,
+        ],
+    )?;
+
+    // Paths inside the repository are the same for both trees.
+    let to_worktree = |p: &path::Path| -> Result<String, Error> {
+        let p = fs::canonicalize(p).map_err(|e| Error::OpenFailed(p.to_owned(), e))?;
+        let rel = p.strip_prefix(&toplevel).unwrap_or(&p);
+        path_str(&worktree.join(rel))
+    };
+
+    let base = to_worktree(path::Path::new(workspace_root)).and_then(|base_root| {
+        let mut base_options = args.options.clone();
+        base_options.manifest_path = Some(to_worktree(&manifest_path)?);
+        base_options.target_dir = Some(path_str(&bloat_dir.join("compare-target"))?);
+        build(&base_options, &base_root)
+    });
+
+    // Remove the worktree even when the build has failed.
+    // The target directory is kept to speed up the next comparison.
+    git(
+        &toplevel,
+        &["worktree", "remove", "--force", &path_str(&worktree)?],
+    )?;
+    let base = base?;
+    print_warnings(&base);
+    eprintln!();
+
+    let entries = |list: &[CrateData]| list.iter().map(|d| Entry::new(d, args)).collect();
+    let comparisons = compare(entries(&base), entries(&head));
+    match args.message_format {
+        MessageFormat::Table => print_table(&comparisons, rev, args.options.n),
+        MessageFormat::Json => print_json(&comparisons, rev, &commit, args.options.n),
+    }
+
+    Ok(())
+}
+
+/// Runs git in the specified directory and returns its trimmed output.
+fn git(dir: &str, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(Error::GitFailed)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let msg = stderr.trim();
+        let msg = msg.strip_prefix("fatal: ").unwrap_or(msg);
+        return Err(Error::GitError(msg.to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns the manifest that `cargo build` would use.
+fn locate_manifest(options: &Options) -> Result<path::PathBuf, Error> {
+    let mut cmd = cargo_command(options);
+    cmd.args(["locate-project", "--message-format=plain"]);
+    if let Some(ref path) = options.manifest_path {
+        cmd.arg(format!("--manifest-path={}", path));
+    }
+
+    let output = cmd.output().map_err(Error::CargoFailed)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let msg = stderr.trim();
+        return Err(Error::CargoError(
+            msg.strip_prefix("error: ").unwrap_or(msg).to_string(),
+        ));
+    }
+
+    Ok(path::PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+fn path_str(path: &path::Path) -> Result<String, Error> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::NonUtf8Path(path.to_owned()))
+}
+
+/// Matches binaries by their file names.
+pub(crate) fn compare(mut base: Vec<Entry>, head: Vec<Entry>) -> Vec<Comparison> {
+    let mut list = Vec::new();
+    for head in head {
+        let base = base
+            .iter()
+            .position(|e| e.name == head.name)
+            .map(|idx| base.remove(idx));
+        list.push(Comparison {
+            name: head.path.clone(),
+            base,
+            head: Some(head),
+        });
+    }
+
+    // Binaries that are gone.
+    for entry in base {
+        list.push(Comparison {
+            name: entry.path.clone(),
+            base: Some(entry),
+            head: None,
+        });
+    }
+
+    list
+}
+
+fn changes<F>(c: &Comparison, n: usize, f: F) -> Vec<(String, i64)>
+where
+    F: Fn(&Entry) -> &HashMap<String, u64>,
+{
+    let empty = HashMap::new();
+    let base = c.base.as_ref().map(&f).unwrap_or(&empty);
+    let head = c.head.as_ref().map(&f).unwrap_or(&empty);
+    let mut list = diff_sizes(base, head);
+    if n != 0 {
+        list.truncate(n);
+    }
+
+    list
+}
+
+fn print_table(comparisons: &[Comparison], rev: &str, n: usize) {
+    for (idx, c) in comparisons.iter().enumerate() {
+        if idx != 0 {
+            println!();
+        }
+
+        eprintln!("    Comparing {} with {}", c.name, rev);
+        eprintln!();
+
+        let mut table = Table::new(&["File", "Diff", ".text", "Diff", "Revision"]);
+        let sizes = |e: &Option<Entry>| e.as_ref().map_or((0, 0), |e| (e.file_size, e.text_size));
+        let (base_file, base_text) = sizes(&c.base);
+        let (head_file, head_text) = sizes(&c.head);
+        table.push(&[
+            format_size(base_file),
+            String::new(),
+            format_size(base_text),
+            String::new(),
+            rev.to_string(),
+        ]);
+        table.push(&[
+            format_size(head_file),
+            format_size_diff(head_file as i64 - base_file as i64),
+            format_size(head_text),
+            format_size_diff(head_text as i64 - base_text as i64),
+            "working tree".to_string(),
+        ]);
+        print!("{}", table);
+
+        print_changes(c, n);
+    }
+}
+
+/// Prints crates and functions that changed the most.
+pub(crate) fn print_changes(c: &Comparison, n: usize) {
+    let sections = [
+        ("Crate", changes(c, n, |e| &e.crates)),
+        ("Function", changes(c, n, |e| &e.symbols)),
+    ];
+    for (title, list) in sections.iter() {
+        if list.is_empty() {
+            continue;
+        }
+
+        println!();
+        let mut table = Table::new(&["Diff", title]);
+        for (name, diff) in list {
+            table.push(&[format_size_diff(*diff), name.clone()]);
+        }
+        print!("{}", table);
+    }
+}
+
+fn print_json(comparisons: &[Comparison], rev: &str, commit: &str, n: usize) {
+    let to_json = |list: Vec<(String, i64)>| {
+        let mut items = json::JsonValue::new_array();
+        for (name, diff) in list {
+            let mut map = json::JsonValue::new_object();
+            map["name"] = name.into();
+            map["diff"] = diff.into();
+            items.push(map).unwrap();
+        }
+        items
+    };
+
+    let mut items = json::JsonValue::new_array();
+    for c in comparisons {
+        let mut map = json::JsonValue::new_object();
+        map["name"] = c.name.clone().into();
+        map["base-file-size"] = c.base.as_ref().map(|e| e.file_size).into();
+        map["base-text-section-size"] = c.base.as_ref().map(|e| e.text_size).into();
+        map["file-size"] = c.head.as_ref().map(|e| e.file_size).into();
+        map["text-section-size"] = c.head.as_ref().map(|e| e.text_size).into();
+        map["crate-changes"] = to_json(changes(c, n, |e| &e.crates));
+        map["function-changes"] = to_json(changes(c, n, |e| &e.symbols));
+        items.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["base"] = rev.into();
+    root["base-commit"] = commit.into();
+    root["binaries"] = items;
+
+    println!("{}", root.dump());
+}
@@ -0,0 +1,308 @@
+//! Binary size history.
+//!
+//! Each `--record` run appends a line per binary to `target/bloat/history.jsonl`.
+
+use std::io::Write;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io, path};
+
+use cargo_bloat::{bloat_dir, crate_sizes, CrateData, Error};
+
+use crate::print::{format_size, format_size_diff};
+use crate::table::Table;
+use crate::{Args, MessageFormat};
+
+const FILE_NAME: &str = "history.jsonl";
+
+const SPARKS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Appends a summary of each binary to the history file.
+pub fn record(args: &Args, workspace_root: &str, crates: &[CrateData]) -> Result<(), Error> {
+    let path = bloat_dir(&args.options, workspace_root)?.join(FILE_NAME);
+
+    let date = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (commit, dirty) = git_commit(workspace_root);
+
+    let mut text = String::new();
+    for d in crates {
+        let mut entry = json::JsonValue::new_object();
+        entry["date"] = date.into();
+        entry["commit"] = commit.clone().into();
+        entry["dirty"] = dirty.into();
+        entry["binary"] = binary_name(d.exe_path.as_deref().unwrap_or("")).into();
+        entry["profile"] = args.options.get_profile().into();
+        entry["file-size"] = d.data.file_size.into();
+        entry["text-section-size"] = d.data.text_size.into();
+
+        let mut sizes: Vec<(String, u64)> = crate_sizes(d, &args.options).into_iter().collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let mut crates = json::JsonValue::new_object();
+        for (name, size) in sizes {
+            crates[name.as_str()] = size.into();
+        }
+        entry["crates"] = crates;
+
+        let mut symbols: Vec<_> = d.data.symbols.iter().collect();
+        symbols.sort_by_key(|s| std::cmp::Reverse(s.size));
+        let n = if args.options.n == 0 {
+            symbols.len()
+        } else {
+            args.options.n
+        };
+        let mut items = json::JsonValue::new_array();
+        for sym in symbols.into_iter().take(n) {
+            let mut map = json::JsonValue::new_object();
+            map["name"] = sym.name.trimmed.clone().into();
+            map["size"] = sym.size.into();
+            items.push(map).unwrap();
+        }
+        entry["symbols"] = items;
+
+        text.push_str(&entry.dump());
+        text.push('\n');
+    }
+
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| Error::OpenFailed(path.clone(), e))?;
+
+    eprintln!("    Recorded to {}", path.display());
+    Ok(())
+}
+
+/// Returns a binary file name, like `app` for `/home/user/app/target/release/app`.
+///
+/// The directory is not stored, so the history survives moving the checkout
+/// or changing the target directory.
+fn binary_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Returns the current commit and whether the working tree has changes.
+fn git_commit(dir: &str) -> (Option<String>, bool) {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let commit = git(&["rev-parse", "HEAD"]);
+    let dirty = commit.is_some()
+        && git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|s| !s.is_empty());
+    (commit, dirty)
+}
+
+/// Prints the recorded history of each binary.
+pub fn print(args: &Args, workspace_root: &str, term_width: Option<usize>) -> Result<(), Error> {
+    let path = bloat_dir(&args.options, workspace_root)?.join(FILE_NAME);
+    let entries = load(&path)?;
+    if entries.is_empty() {
+        return Err(Error::NoHistory(path));
+    }
+
+    if args.message_format == MessageFormat::Json {
+        let mut root = json::JsonValue::new_object();
+        root["history"] = entries.into();
+        println!("{}", root.dump());
+        return Ok(());
+    }
+
+    // Group by binary and profile, preserving the order.
+    // Older entries store the full path, so only the file name is compared.
+    let mut binaries: Vec<((&str, &str), Vec<&json::JsonValue>)> = Vec::new();
+    for entry in &entries {
+        let key = (
+            binary_name(entry["binary"].as_str().unwrap_or("")),
+            entry["profile"].as_str().unwrap_or(""),
+        );
+        match binaries.iter_mut().find(|v| v.0 == key) {
+            Some(v) => v.1.push(entry),
+            None => binaries.push((key, vec![entry])),
+        }
+    }
+
+    for (idx, ((binary, profile), entries)) in binaries.iter().enumerate() {
+        if idx != 0 {
+            println!();
+        }
+
+        let n = if args.options.n == 0 {
+            entries.len()
+        } else {
+            args.options.n
+        };
+        let entries = &entries[entries.len().saturating_sub(n)..];
+
+        eprintln!("    History of {} ({})", binary, profile);
+        eprintln!();
+
+        print_entries(entries, term_width);
+        println!();
+        print_trends(entries, args.options.n, term_width);
+    }
+
+    Ok(())
+}
+
+fn load(path: &path::Path) -> Result<Vec<json::JsonValue>, Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::OpenFailed(path.to_owned(), e)),
+    };
+
+    let mut entries = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        match json::parse(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => eprintln!(
+                "Warning: skipping invalid line {} in '{}'.",
+                idx + 1,
+                path.display()
+            ),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn print_entries(entries: &[&json::JsonValue], term_width: Option<usize>) {
+    let mut table = Table::new(&["Date (UTC)", "File", "Diff", ".text", "Diff", "Commit"]);
+    table.set_width(term_width);
+
+    let mut prev: Option<&json::JsonValue> = None;
+    for entry in entries {
+        let file_size = entry["file-size"].as_u64().unwrap_or(0);
+        let text_size = entry["text-section-size"].as_u64().unwrap_or(0);
+        let diff = |key: &str, size: u64| match prev {
+            Some(prev) => format_size_diff(size as i64 - prev[key].as_u64().unwrap_or(0) as i64),
+            None => String::new(),
+        };
+
+        let mut commit: String = entry["commit"]
+            .as_str()
+            .unwrap_or("")
+            .chars()
+            .take(10)
+            .collect();
+        if entry["dirty"].as_bool() == Some(true) {
+            commit.push_str(" (dirty)");
+        }
+
+        table.push(&[
+            format_date(entry["date"].as_u64().unwrap_or(0)),
+            format_size(file_size),
+            diff("file-size", file_size),
+            format_size(text_size),
+            diff("text-section-size", text_size),
+            commit,
+        ]);
+
+        prev = Some(entry);
+    }
+
+    print!("{}", table);
+}
+
+/// Prints sparklines of the total sizes and of the biggest crates in the last entry.
+fn print_trends(entries: &[&json::JsonValue], n: usize, term_width: Option<usize>) {
+    let mut table = Table::new(&["Trend", "Size", "Change", "Name"]);
+    table.set_width(term_width);
+
+    let mut push = |values: Vec<u64>, name: String| {
+        let first = values.first().cloned().unwrap_or(0);
+        let last = values.last().cloned().unwrap_or(0);
+        table.push(&[
+            sparkline(&values),
+            format_size(last),
+            format_size_diff(last as i64 - first as i64),
+            name,
+        ]);
+    };
+
+    let sizes = |key: &str| {
+        entries
+            .iter()
+            .map(|e| e[key].as_u64().unwrap_or(0))
+            .collect()
+    };
+    push(sizes("file-size"), "File size".to_string());
+    push(sizes("text-section-size"), ".text section size".to_string());
+
+    let last = match entries.last() {
+        Some(v) => v,
+        None => return,
+    };
+
+    let mut crates: Vec<(&str, u64)> = last["crates"]
+        .entries()
+        .map(|(name, size)| (name, size.as_u64().unwrap_or(0)))
+        .collect();
+    crates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let n = if n == 0 { crates.len() } else { n };
+    for (name, _) in crates.into_iter().take(n) {
+        let values = entries
+            .iter()
+            .map(|e| e["crates"][name].as_u64().unwrap_or(0))
+            .collect();
+        push(values, name.to_string());
+    }
+
+    print!("{}", table);
+}
+
+fn sparkline(values: &[u64]) -> String {
+    let min = values.iter().cloned().min().unwrap_or(0);
+    let max = values.iter().cloned().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|v| {
+            if max == min {
+                SPARKS[0]
+            } else {
+                let idx = (v - min) as f64 / (max - min) as f64 * (SPARKS.len() - 1) as f64;
+                SPARKS[idx.round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// Formats a UNIX timestamp as `YYYY-MM-DD HH:MM`.
+fn format_date(secs: u64) -> String {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let time = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60
+    )
+}
@@ -0,0 +1,609 @@
+use std::process;
+
+use cargo_bloat::{config, CrateData, Error, Options};
+
+mod budget;
+mod compare;
+mod history;
+mod matrix;
+mod print;
+mod table;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
+mod wrapper;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum MessageFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// Command line arguments.
+///
+/// Build and analysis options are passed to the library as is.
+#[derive(Clone, Default)]
+struct Args {
+    help: bool,
+    version: bool,
+    crates: bool,
+    by_object: bool,
+    by_source: bool,
+    panic_cost: bool,
+    no_relative_size: bool,
+    wide: bool,
+    message_format: MessageFormat,
+    matrix: Vec<String>,
+    record: bool,
+    history: bool,
+    compare_rev: Option<String>,
+    watch: bool,
+    tui: bool,
+    budget: budget::Budget,
+    options: Options,
+}
+
+impl Args {
+    fn new() -> Self {
+        Args {
+            options: Options {
+                cargo_output: true,
+                ..Options::new()
+            },
+            ..Args::default()
+        }
+    }
+}
+
+fn main() {
+    if let Ok(wrap) = std::env::var("RUSTC_WRAPPER") {
+        if wrap.contains("cargo-bloat") {
+            let args: Vec<_> = std::env::args().collect();
+            match wrapper::run(&args) {
+                Ok(_) => return,
+                Err(e) => {
+                    eprintln!("Error: {}.", e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    let mut raw_args: Vec<_> = std::env::args_os().collect();
+    raw_args.remove(0); // file path
+    if raw_args.first().and_then(|s| s.to_str()) == Some("bloat") {
+        raw_args.remove(0);
+    } else {
+        eprintln!("Error: can be run only via `cargo bloat`.");
+        process::exit(1);
+    }
+
+    // Arguments may be invalid, so check the message format separately.
+    let cli_message_format = raw_message_format(&raw_args);
+    let mut json_errors = cli_message_format.as_deref() == Some("json");
+
+    let (mut args, remaining) = match parse_args(raw_args.clone(), Args::new()) {
+        Ok(v) => v,
+        Err(e) => {
+            // The message format could be set by the config, which is found using defaults.
+            let json_errors = json_errors
+                || (cli_message_format.is_none()
+                    && cargo_bloat::workspace_metadata(&Args::new().options)
+                        .map(|m| config_message_format_is_json(&m.config))
+                        .unwrap_or(false));
+            exit_with_error(Error::InvalidArgs(e.to_string()), json_errors)
+        }
+    };
+
+    if !remaining.is_empty() {
+        eprintln!("Warning: unused arguments left: {:?}.", remaining);
+    }
+
+    if args.help {
+        println!("{}", HELP);
+        return;
+    }
+
+    if args.version {
+        println!("{}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    let metadata = match cargo_bloat::workspace_metadata(&args.options) {
+        Ok(v) => v,
+        Err(e) => exit_with_error(e, json_errors),
+    };
+
+    // The command line takes precedence, like for any other option.
+    if cli_message_format.is_none() {
+        json_errors = config_message_format_is_json(&metadata.config);
+    }
+
+    if !metadata.config.is_empty() {
+        args = match apply_config(raw_args, &metadata.config) {
+            Ok(v) => v,
+            Err(e) => exit_with_error(e, json_errors),
+        };
+    }
+
+    let json_errors = args.message_format == MessageFormat::Json;
+
+    if let Err(e) = check_conflicts(&args) {
+        exit_with_error(e, json_errors);
+    }
+
+    let term_width = if !args.wide {
+        term_size::dimensions().map(|v| v.0)
+    } else {
+        None
+    };
+
+    if args.history {
+        if let Err(e) = history::print(&args, &metadata.root, term_width) {
+            exit_with_error(e, json_errors);
+        }
+
+        return;
+    }
+
+    if !args.matrix.is_empty() {
+        if let Err(e) = matrix::run(&args, &metadata.root) {
+            exit_with_error(e, json_errors);
+        }
+
+        return;
+    }
+
+    if args.watch {
+        if let Err(e) = watch::run(&args, &metadata.root, term_width) {
+            exit_with_error(e, json_errors);
+        }
+
+        return;
+    }
+
+    if let Some(ref rev) = args.compare_rev {
+        if let Err(e) = compare::run(&args, &metadata.root, rev) {
+            exit_with_error(e, json_errors);
+        }
+
+        return;
+    }
+
+    let mut crates = match cargo_bloat::build(&args.options, &metadata.root) {
+        Ok(v) => v,
+        Err(e) => exit_with_error(e, json_errors),
+    };
+    print::print_warnings(&crates);
+
+    // Record before the interactive UI, which returns early.
+    if args.record {
+        if let Err(e) = history::record(&args, &metadata.root, &crates) {
+            exit_with_error(e, json_errors);
+        }
+    }
+
+    if args.tui {
+        match run_tui(&crates, &args) {
+            Ok(true) => return,
+            Ok(false) => {}
+            Err(e) => exit_with_error(e, json_errors),
+        }
+    }
+
+    print::print_binaries(&mut crates, &args, term_width);
+    let nearly_empty = crates.iter().any(|d| d.data.symbols.len() < 10);
+
+    let exceeded = budget::check(&crates, &args);
+
+    if args.message_format == MessageFormat::Table {
+        if args.crates || args.panic_cost {
+            println!();
+            println!(
+                "Note: numbers above are a result of guesswork. \
+                      They are not 100% correct and never will be."
+            );
+        }
+
+        if nearly_empty {
+            println!();
+            println!(
+                "Warning: it seems like the `.text` section is nearly empty. \
+                      Try removing `strip = true` from Cargo.toml"
+            );
+        }
+    }
+
+    if !exceeded.is_empty() {
+        eprintln!();
+        for msg in &exceeded {
+            eprintln!("Error: {}.", msg);
+        }
+
+        process::exit(1);
+    }
+}
+
+const HELP: &str = "\
+Find out what takes most of the space in your executable
+
+USAGE:
+    cargo bloat [OPTIONS] [-- <CARGO BUILD ARGS>...]
+
+OPTIONS:
+    -h, --help                      Prints help information
+    -V, --version                   Prints version information
+        --lib                       Build only this package's library
+        --bin <NAME>                Build only the specified binary
+        --example <NAME>            Build only the specified example
+        --test <NAME>               Build only the specified test target
+        --bench <NAME>              Build only the specified bench target
+        --bins                      Build all binaries
+        --examples                  Build all examples
+        --all-targets               Build all targets
+    -p, --package <SPEC>            Package to build
+        --release                   Build artifacts in release mode, with optimizations
+    -j, --jobs <N>                  Number of parallel jobs, defaults to # of CPUs
+        --features <FEATURES>       Space-separated list of features to activate
+        --all-features              Activate all available features
+        --no-default-features       Do not activate the `default` feature
+        --profile <PROFILE>         Build with the given profile.
+        --config <CONFIG>...        Build with the given cargo config
+        --target <TARGET>           Build for the target triple
+        --target-dir <DIRECTORY>    Directory for all generated artifacts
+        --frozen                    Require Cargo.lock and cache are up to date
+        --locked                    Require Cargo.lock is up to date
+    -Z <FLAG>...                    Unstable (nightly-only) flags to Cargo, see 'cargo -Z help' for details
+        --crates                    Per crate bloatedness
+        --by-object                 Per object file bloatedness (with a PDB or a linker map)
+        --by-source                 Per source file bloatedness, including inlined code (with a PDB)
+        --filter <CRATE|REGEXP>     Filter functions by crate
+        --panic-cost                Show the size of panicking and formatting machinery
+        --split-std                 Split the 'std' crate to original crates like core, alloc, etc.
+        --sysroot <DIR>             Use std libraries from a custom sysroot
+        --toolchain <NAME>          Use cargo and rustc from a rustup toolchain, like 'nightly'
+        --std-rlibs <DIR>           Use std libraries from a directory with rlibs
+        --symbols-section <NAME>    Use custom symbols section, like '.rodata' or '__TEXT,__const'
+                                    [default: .text or __TEXT,__text]
+        --arch <NAME>               Analyze only one architecture of a universal Mach-O binary
+        --pdb <PATH>                Use a PDB file or a symbol store directory (MSVC-only)
+        --embedded                  Report flash and RAM usage of an embedded target (ELF-only)
+        --linker-map <FILE>         Use a linker map (GNU ld, lld or MSVC) as a symbols source
+        --emit-linker-map           Ask the linker to produce a map and use it
+        --no-relative-size          Hide 'File' and '.text' columns
+        --full-fn                   Print full function name with hash values
+        --matrix <KEY=VALUE,...>    Compare sizes across profile settings, like 'opt-level=s,z'
+        --record                    Append the results to target/bloat/history.jsonl
+        --history                   Show the size history saved by --record
+        --compare-rev <REV>         Compare with a git revision, like 'origin/main'
+        --watch                     Rebuild and analyze again when sources are changed
+        --tui                       Browse the results in an interactive terminal UI
+    -n <NUM>                        Number of lines to show, 0 to show all [default: 20]
+    -w, --wide                      Do not trim long function names
+        --message-format <FMT>      Output format [default: table] [possible values: table, json]
+        --max-file-size <SIZE>      Fail when the file size exceeds the budget, like '2MiB'
+        --max-text-size <SIZE>      Fail when the .text section size exceeds the budget
+        --max-crate-size <CRATE=SIZE>...
+                                    Fail when a crate size exceeds the budget, like 'regex=200KiB'
+
+Flags can be turned off with --no-<FLAG>, like --no-crates, to override the config.
+--no-relative-size and --no-default-features are turned off with --relative-size
+and --default-features.
+
+ARGS:
+    <CARGO BUILD ARGS>...           Arguments passed to 'cargo build' as is, like '--workspace' or '--offline'
+";
+
+fn parse_message_format(s: &str) -> Result<MessageFormat, &'static str> {
+    match s {
+        "table" => Ok(MessageFormat::Table),
+        "json" => Ok(MessageFormat::Json),
+        _ => Err("invalid message format"),
+    }
+}
+
+/// Parses command line arguments on top of `defaults`.
+///
+/// Returns the arguments that were not recognized as well.
+fn parse_args(
+    mut raw_args: Vec<std::ffi::OsString>,
+    defaults: Args,
+) -> Result<(Args, Vec<std::ffi::OsString>), pico_args::Error> {
+    // Everything after `--` is forwarded to `cargo build` as is.
+    let cargo_args = match raw_args.iter().position(|arg| arg == "--") {
+        Some(idx) => {
+            let cargo_args = raw_args.split_off(idx + 1);
+            raw_args.pop(); // --
+            cargo_args
+        }
+        None => Vec::new(),
+    };
+
+    let d = defaults;
+    let o = d.options;
+    let mut input = pico_args::Arguments::from_vec(raw_args);
+    let mut args = Args {
+        help: input.contains(["-h", "--help"]),
+        version: input.contains(["-V", "--version"]),
+        crates: switch(&mut input, "--crates", "--no-crates", d.crates),
+        by_object: switch(&mut input, "--by-object", "--no-by-object", d.by_object),
+        by_source: switch(&mut input, "--by-source", "--no-by-source", d.by_source),
+        panic_cost: switch(&mut input, "--panic-cost", "--no-panic-cost", d.panic_cost),
+        no_relative_size: switch(
+            &mut input,
+            "--no-relative-size",
+            "--relative-size",
+            d.no_relative_size,
+        ),
+        wide: switch(&mut input, ["-w", "--wide"], "--no-wide", d.wide),
+        message_format: input
+            .opt_value_from_fn("--message-format", parse_message_format)?
+            .unwrap_or(d.message_format),
+        matrix: input.values_from_str("--matrix")?,
+        record: switch(&mut input, "--record", "--no-record", d.record),
+        history: switch(&mut input, "--history", "--no-history", d.history),
+        compare_rev: input.opt_value_from_str("--compare-rev")?.or(d.compare_rev),
+        watch: switch(&mut input, "--watch", "--no-watch", d.watch),
+        tui: switch(&mut input, "--tui", "--no-tui", d.tui),
+        budget: budget::Budget {
+            file_size: input
+                .opt_value_from_fn("--max-file-size", budget::parse_size)?
+                .or(d.budget.file_size),
+            text_size: input
+                .opt_value_from_fn("--max-text-size", budget::parse_size)?
+                .or(d.budget.text_size),
+            crates: input.values_from_fn("--max-crate-size", budget::parse_crate_size)?,
+        },
+        options: Options {
+            lib: switch(&mut input, "--lib", "--no-lib", o.lib),
+            bin: input.opt_value_from_str("--bin")?.or(o.bin),
+            example: input.opt_value_from_str("--example")?.or(o.example),
+            test: input.opt_value_from_str("--test")?.or(o.test),
+            bench: input.opt_value_from_str("--bench")?.or(o.bench),
+            bins: switch(&mut input, "--bins", "--no-bins", o.bins),
+            examples: switch(&mut input, "--examples", "--no-examples", o.examples),
+            all_targets: switch(
+                &mut input,
+                "--all-targets",
+                "--no-all-targets",
+                o.all_targets,
+            ),
+            package: input.opt_value_from_str(["-p", "--package"])?.or(o.package),
+            release: switch(&mut input, "--release", "--no-release", o.release),
+            jobs: input.opt_value_from_str(["-j", "--jobs"])?.or(o.jobs),
+            features: input.opt_value_from_str("--features")?.or(o.features),
+            all_features: switch(
+                &mut input,
+                "--all-features",
+                "--no-all-features",
+                o.all_features,
+            ),
+            no_default_features: switch(
+                &mut input,
+                "--no-default-features",
+                "--default-features",
+                o.no_default_features,
+            ),
+            profile: input.opt_value_from_str("--profile")?.or(o.profile),
+            profile_overrides: Vec::new(),
+            config: input.values_from_str("--config")?,
+            target: input.opt_value_from_str("--target")?.or(o.target),
+            target_dir: input.opt_value_from_str("--target-dir")?.or(o.target_dir),
+            frozen: switch(&mut input, "--frozen", "--no-frozen", o.frozen),
+            locked: switch(&mut input, "--locked", "--no-locked", o.locked),
+            unstable: input.values_from_str("-Z")?,
+            manifest_path: input
+                .opt_value_from_str("--manifest-path")?
+                .or(o.manifest_path),
+            verbose: switch(&mut input, ["-v", "--verbose"], "--no-verbose", o.verbose),
+            cargo_args: Vec::new(),
+            cargo_output: true,
+            filter: input.opt_value_from_str("--filter")?.or(o.filter),
+            split_std: switch(&mut input, "--split-std", "--no-split-std", o.split_std),
+            full_fn: switch(&mut input, "--full-fn", "--no-full-fn", o.full_fn),
+            n: input.opt_value_from_str("-n")?.unwrap_or(o.n),
+            sysroot: input.opt_value_from_str("--sysroot")?.or(o.sysroot),
+            std_rlibs: input.opt_value_from_str("--std-rlibs")?.or(o.std_rlibs),
+            toolchain: input
+                .opt_value_from_str::<_, String>("--toolchain")?
+                .map(|v| v.trim_start_matches('+').to_string())
+                .or(o.toolchain),
+            symbols_section: input
+                .opt_value_from_str("--symbols-section")?
+                .or(o.symbols_section),
+            arch: input.opt_value_from_str("--arch")?.or(o.arch),
+            pdb: input.opt_value_from_str("--pdb")?.or(o.pdb),
+            embedded: switch(&mut input, "--embedded", "--no-embedded", o.embedded),
+            linker_map: input.opt_value_from_str("--linker-map")?.or(o.linker_map),
+            emit_linker_map: switch(
+                &mut input,
+                "--emit-linker-map",
+                "--no-emit-linker-map",
+                o.emit_linker_map,
+            ),
+        },
+    };
+
+    if args.options.unstable.is_empty() {
+        args.options.unstable = o.unstable;
+    }
+
+    if args.options.config.is_empty() {
+        args.options.config = o.config;
+    }
+
+    if args.matrix.is_empty() {
+        args.matrix = d.matrix;
+    }
+
+    if args.budget.crates.is_empty() {
+        args.budget.crates = d.budget.crates;
+    }
+
+    let remaining = input.finish();
+
+    // The profile, the target, the package and the directories affect how we are locating
+    // the artifacts, std and the config, therefore we have to know them even when they are
+    // set after `--`.
+    let mut input = pico_args::Arguments::from_vec(cargo_args);
+    args.options.release |= input.contains(["-r", "--release"]);
+    if let Some(profile) = input.opt_value_from_str("--profile")? {
+        args.options.profile = Some(profile);
+    }
+    if let Some(target) = input.opt_value_from_str("--target")? {
+        args.options.target = Some(target);
+    }
+    if let Some(dir) = input.opt_value_from_str("--target-dir")? {
+        args.options.target_dir = Some(dir);
+    }
+    if let Some(path) = input.opt_value_from_str("--manifest-path")? {
+        args.options.manifest_path = Some(path);
+    }
+    if let Some(package) = input.opt_value_from_str(["-p", "--package"])? {
+        args.options.package = Some(package);
+    }
+
+    args.options.cargo_args = input
+        .finish()
+        .into_iter()
+        .map(|arg| {
+            arg.into_string()
+                .map_err(|_| pico_args::Error::NonUtf8Argument)
+        })
+        .collect::<Result<_, _>>()?;
+
+    if args.options.cargo_args.is_empty() {
+        args.options.cargo_args = o.cargo_args;
+    }
+
+    Ok((args, remaining))
+}
+
+/// Returns the message format from the command line, even when other arguments are invalid.
+///
+/// Arguments after `--` are passed to `cargo build`, so they are ignored.
+fn raw_message_format(raw_args: &[std::ffi::OsString]) -> Option<String> {
+    let mut args = raw_args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .filter_map(|arg| arg.to_str());
+    while let Some(arg) = args.next() {
+        if arg == "--message-format" {
+            return args.next().map(str::to_string);
+        } else if let Some(value) = arg.strip_prefix("--message-format=") {
+            return Some(value.to_string());
+        }
+    }
+
+    None
+}
+
+fn config_message_format_is_json(config: &json::JsonValue) -> bool {
+    ["message-format", "message_format"]
+        .iter()
+        .any(|key| config[*key].as_str() == Some("json"))
+}
+
+/// Parses a flag that can be turned off by its `--no-` counterpart,
+/// which is useful when the config enables it.
+fn switch(
+    input: &mut pico_args::Arguments,
+    on: impl Into<pico_args::Keys>,
+    off: &'static str,
+    default: bool,
+) -> bool {
+    let on = input.contains(on);
+    let off = input.contains(off);
+    !off && (on || default)
+}
+
+/// Prints an error and exits.
+///
+/// JSON errors are printed to stdout, like the rest of the JSON output.
+fn exit_with_error(e: Error, json: bool) -> ! {
+    if json {
+        println!("{}", e.to_json().dump());
+    } else {
+        eprintln!("Error: {}.", e);
+    }
+
+    process::exit(1);
+}
+
+/// Rejects options that cannot be used together.
+///
+/// Checked after the config is applied, since a conflicting option can come from it as well.
+fn check_conflicts(args: &Args) -> Result<(), Error> {
+    // The build output is parsed using `--message-format=json-render-diagnostics`.
+    let message_format = args
+        .options
+        .cargo_args
+        .iter()
+        .any(|arg| arg == "--message-format" || arg.starts_with("--message-format="));
+    if message_format {
+        return Err(Error::InvalidArgs(
+            "--message-format cannot be passed to cargo build".to_string(),
+        ));
+    }
+
+    if args.tui {
+        let reports = [
+            ("--panic-cost", args.panic_cost),
+            ("--by-object", args.by_object),
+            ("--by-source", args.by_source),
+        ];
+        for (name, enabled) in reports.iter() {
+            if *enabled {
+                return Err(Error::InvalidArgs(format!(
+                    "{} cannot be used with --tui",
+                    name
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `false` when the interactive UI is not available and the table should be printed.
+#[cfg(feature = "tui")]
+fn run_tui(crates: &[CrateData], args: &Args) -> Result<bool, Error> {
+    use std::io::IsTerminal;
+
+    if args.message_format != MessageFormat::Table || !std::io::stdout().is_terminal() {
+        eprintln!("Warning: --tui requires a terminal. Printing the results as is.");
+        return Ok(false);
+    }
+
+    tui::run(crates, args)?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_: &[CrateData], _: &Args) -> Result<bool, Error> {
+    eprintln!(
+        "Warning: cargo-bloat was built without the 'tui' feature. Printing the results as is."
+    );
+    Ok(false)
+}
+
+/// Parses command line arguments using the config values as defaults.
+///
+/// Only errors in the config values are reported as an invalid config.
+fn apply_config(
+    raw_args: Vec<std::ffi::OsString>,
+    config: &json::JsonValue,
+) -> Result<Args, Error> {
+    let config_args = config::to_args(config).map_err(Error::InvalidConfig)?;
+    let (defaults, remaining) =
+        parse_args(config_args, Args::new()).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+
+    if !remaining.is_empty() {
+        eprintln!("Warning: unknown config options: {:?}.", remaining);
+    }
+
+    let (args, _) =
+        parse_args(raw_args, defaults).map_err(|e| Error::InvalidArgs(e.to_string()))?;
+    Ok(args)
+}
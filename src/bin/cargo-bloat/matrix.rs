@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use cargo_bloat::{build, crate_sizes, diff_sizes, Error};
+
+use crate::print::{format_size, format_size_diff, print_warnings};
+use crate::table::Table;
+use crate::{Args, MessageFormat};
+
+/// Number of the most changed crates to show per configuration.
+const TOP_CRATES: usize = 5;
+
+struct Entry {
+    name: String,
+    file_size: u64,
+    text_size: u64,
+    crates: HashMap<String, u64>,
+}
+
+/// Builds and analyzes the target once per combination of profile overrides.
+///
+/// Each `spec` has a `KEY=VALUE,...` format, like `opt-level=s,z`.
+/// The first entry is always the profile as is.
+pub fn run(args: &Args, workspace_root: &str) -> Result<(), Error> {
+    let mut configs: Vec<Vec<(String, String)>> = vec![Vec::new()];
+    for spec in &args.matrix {
+        let (key, values) = parse_spec(spec)?;
+
+        let mut new_configs = Vec::new();
+        for config in &configs {
+            for value in &values {
+                let mut config = config.clone();
+                config.push((key.clone(), value.clone()));
+                new_configs.push(config);
+            }
+        }
+        configs = new_configs;
+    }
+
+    // Compare against the unmodified profile.
+    if !configs.iter().any(|c| c.is_empty()) {
+        configs.insert(0, Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for config in &configs {
+        let name = config_name(config);
+        eprintln!("     Building {}", name);
+
+        let mut options = args.options.clone();
+        options.profile_overrides = config.clone();
+        let mut crates = build(&options, workspace_root)?;
+        print_warnings(&crates);
+        if crates.len() > 1 {
+            eprintln!(
+                "Warning: multiple artifacts were produced. Only the first one will be compared."
+            );
+        }
+
+        let crate_data = crates.remove(0);
+        entries.push(Entry {
+            name,
+            file_size: crate_data.data.file_size,
+            text_size: crate_data.data.text_size,
+            crates: crate_sizes(&crate_data, &args.options),
+        });
+    }
+    eprintln!();
+
+    match args.message_format {
+        MessageFormat::Table => print_table(&entries),
+        MessageFormat::Json => print_json(&entries),
+    }
+
+    Ok(())
+}
+
+fn parse_spec(spec: &str) -> Result<(String, Vec<String>), Error> {
+    let mut parts = spec.splitn(2, '=');
+    let key = parts.next().unwrap_or("").trim();
+    let values: Vec<String> = parts
+        .next()
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if key.is_empty() || values.is_empty() {
+        return Err(Error::InvalidMatrix(spec.to_string()));
+    }
+
+    Ok((key.to_string(), values))
+}
+
+fn config_name(config: &[(String, String)]) -> String {
+    if config.is_empty() {
+        return "[profile defaults]".to_string();
+    }
+
+    let list: Vec<String> = config.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    list.join(" ")
+}
+
+fn crate_changes(base: &Entry, entry: &Entry) -> Vec<(String, i64)> {
+    let mut changes = diff_sizes(&base.crates, &entry.crates);
+    changes.truncate(TOP_CRATES);
+    changes
+}
+
+fn print_table(entries: &[Entry]) {
+    let base = &entries[0];
+
+    let mut table = Table::new(&["File", "Diff", ".text", "Diff", "Configuration"]);
+    for entry in entries {
+        table.push(&[
+            format_size(entry.file_size),
+            format_size_diff(entry.file_size as i64 - base.file_size as i64),
+            format_size(entry.text_size),
+            format_size_diff(entry.text_size as i64 - base.text_size as i64),
+            entry.name.clone(),
+        ]);
+    }
+    print!("{}", table);
+
+    for entry in entries.iter().skip(1) {
+        let changes = crate_changes(base, entry);
+        if changes.is_empty() {
+            continue;
+        }
+
+        println!();
+        println!("Top crate changes for {}:", entry.name);
+
+        let mut table = Table::new(&["Diff", "Crate"]);
+        for (name, diff) in changes {
+            table.push(&[format_size_diff(diff), name]);
+        }
+        print!("{}", table);
+    }
+}
+
+fn print_json(entries: &[Entry]) {
+    let base = &entries[0];
+
+    let mut items = json::JsonValue::new_array();
+    for entry in entries {
+        let mut changes = json::JsonValue::new_array();
+        for (name, diff) in crate_changes(base, entry) {
+            let mut map = json::JsonValue::new_object();
+            map["name"] = name.into();
+            map["diff"] = diff.into();
+            changes.push(map).unwrap();
+        }
+
+        let mut map = json::JsonValue::new_object();
+        map["configuration"] = entry.name.clone().into();
+        map["file-size"] = entry.file_size.into();
+        map["text-section-size"] = entry.text_size.into();
+        map["crate-changes"] = changes;
+
+        items.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["matrix"] = items;
+
+    println!("{}", root.dump());
+}
@@ -0,0 +1,863 @@
+//! Reports printing.
+
+use std::collections::HashMap;
+
+use cargo_bloat::embedded::Memory;
+use cargo_bloat::objects::{self, Objects};
+use cargo_bloat::panic_cost::{self, Report};
+use cargo_bloat::sources::{self, Sources};
+use cargo_bloat::{Crate, CrateData, Crates, Data, Method, Methods, Options};
+
+use crate::table::Table;
+use crate::{Args, MessageFormat};
+
+/// Prints warnings collected during the analysis.
+pub fn print_warnings(crates: &[CrateData]) {
+    for d in crates {
+        for warning in &d.data.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+}
+
+/// Prints reports of all binaries.
+///
+/// Slices of a universal binary are printed as a single table, when the report allows it.
+pub fn print_binaries(crates: &mut [CrateData], args: &Args, term_width: Option<usize>) {
+    let side_by_side = args.message_format == MessageFormat::Table
+        && !args.panic_cost
+        && !args.by_object
+        && !args.by_source;
+
+    let mut reports = Vec::new();
+    let mut idx = 0;
+    while idx < crates.len() {
+        if idx != 0 && args.message_format == MessageFormat::Table {
+            println!();
+        }
+
+        let count = slices_count(&crates[idx..]);
+        if side_by_side && count > 1 && crates[idx].data.memory.is_none() {
+            if let Some(path) = binary_path(&crates[idx]) {
+                eprintln!("    Analyzing {}", path);
+                eprintln!();
+            }
+
+            print_slices_table(&mut crates[idx..idx + count], args, term_width);
+            idx += count;
+            continue;
+        }
+
+        let crate_data = &mut crates[idx];
+        if let Some(ref path) = crate_data.exe_path {
+            eprintln!("    Analyzing {}", path);
+            eprintln!();
+        }
+
+        if let Some(report) = print_crate_data(crate_data, args, term_width) {
+            reports.push((crate_data.exe_path.clone(), report));
+        }
+
+        idx += 1;
+    }
+
+    if args.message_format == MessageFormat::Json {
+        print_json(reports);
+    }
+}
+
+/// Returns a binary path without the architecture suffix.
+fn binary_path(d: &CrateData) -> Option<&str> {
+    let path = d.exe_path.as_deref()?;
+    match d.data.arch {
+        Some(ref arch) => path.strip_suffix(&format!(" [{}]", arch)).or(Some(path)),
+        None => Some(path),
+    }
+}
+
+/// Returns the number of consecutive slices of the same universal binary.
+fn slices_count(crates: &[CrateData]) -> usize {
+    let first = match crates.first() {
+        Some(d) if d.data.arch.is_some() => d,
+        _ => return 1,
+    };
+
+    crates
+        .iter()
+        .take_while(|d| d.data.arch.is_some() && binary_path(d) == binary_path(first))
+        .count()
+}
+
+/// Prints crates or functions of universal binary slices side by side,
+/// with a size column per architecture.
+fn print_slices_table(slices: &mut [CrateData], args: &Args, term_width: Option<usize>) {
+    // Each slice has its own biggest items, so the combined list is trimmed instead.
+    let options = Options {
+        n: 0,
+        ..args.options.clone()
+    };
+
+    let columns = slices.len();
+    let mut rows: Vec<(String, String, Vec<u64>)> = Vec::new(); // crate, name, sizes
+    let mut row_indices: HashMap<(String, String), usize> = HashMap::new();
+    for (col, d) in slices.iter_mut().enumerate() {
+        let items: Vec<(String, String, u64)> = if args.crates {
+            let crates = cargo_bloat::filter_crates(d, &options);
+            crates
+                .crates
+                .into_iter()
+                .map(|c| (c.name, String::new(), c.size))
+                .collect()
+        } else {
+            let methods = cargo_bloat::filter_methods(d, &options);
+            if col == 0 && args.options.filter.is_some() && !methods.has_filter {
+                eprintln!(
+                    "Warning: the filter value contains an unknown crate \
+                           or an invalid regexp. Ignored."
+                );
+            }
+
+            methods
+                .methods
+                .into_iter()
+                .map(|m| (m.crate_name, m.name, m.size))
+                .collect()
+        };
+
+        for (crate_name, name, size) in items {
+            let row = *row_indices
+                .entry((crate_name.clone(), name.clone()))
+                .or_insert_with(|| {
+                    rows.push((crate_name, name, vec![0; columns]));
+                    rows.len() - 1
+                });
+            rows[row].2[col] += size;
+        }
+    }
+
+    let max_size = |sizes: &[u64]| sizes.iter().max().cloned().unwrap_or(0);
+    rows.sort_by(|a, b| max_size(&b.2).cmp(&max_size(&a.2)).then(a.1.cmp(&b.1)));
+
+    let n = if args.options.n == 0 {
+        rows.len()
+    } else {
+        args.options.n
+    };
+
+    let mut header: Vec<&str> = slices
+        .iter()
+        .map(|d| d.data.arch.as_deref().unwrap_or(""))
+        .collect();
+    header.push("Crate");
+    if !args.crates {
+        header.push("Name");
+    }
+
+    let mut table = Table::new(&header);
+    table.set_width(term_width);
+
+    let push_row = |table: &mut Table, sizes: &[u64], crate_name: String, name: String| {
+        let mut row: Vec<String> = sizes.iter().map(|s| format_size(*s)).collect();
+        if args.crates {
+            row.push(crate_name + &name);
+        } else {
+            row.push(crate_name);
+            row.push(name);
+        }
+        table.push(&row);
+    };
+
+    for (crate_name, name, sizes) in rows.iter().take(n) {
+        push_row(&mut table, sizes, crate_name.clone(), name.clone());
+    }
+
+    let others = &rows[n.min(rows.len())..];
+    if !others.is_empty() {
+        let sizes: Vec<u64> = (0..columns)
+            .map(|col| others.iter().map(|v| v.2[col]).sum())
+            .collect();
+        let text = if args.crates {
+            format!("And {} more crates. Use -n N to show more.", others.len())
+        } else {
+            format!(
+                "And {} smaller methods. Use -n N to show more.",
+                others.len()
+            )
+        };
+        push_row(&mut table, &sizes, String::new(), text);
+    }
+
+    let section_name = slices[0].data.section_name.as_deref().unwrap_or(".text");
+    let text_sizes: Vec<u64> = slices.iter().map(|d| d.data.text_size).collect();
+    let file_sizes: Vec<u64> = slices.iter().map(|d| d.data.file_size).collect();
+    push_row(
+        &mut table,
+        &text_sizes,
+        String::new(),
+        format!("{} section size", section_name),
+    );
+    push_row(
+        &mut table,
+        &file_sizes,
+        String::new(),
+        "slice size".to_string(),
+    );
+
+    print!("{}", table);
+}
+
+/// Prints a report selected by `args`, like a crates or a functions list.
+///
+/// A JSON report is returned instead, so reports of all binaries end up in a single document.
+fn print_crate_data(
+    crate_data: &mut CrateData,
+    args: &Args,
+    term_width: Option<usize>,
+) -> Option<json::JsonValue> {
+    if let Some(ref memory) = crate_data.data.memory {
+        if args.message_format == MessageFormat::Table {
+            print_memory_table(memory, args.options.n, term_width);
+        }
+    }
+
+    if args.panic_cost {
+        let report = panic_cost::collect(crate_data);
+        match args.message_format {
+            MessageFormat::Table => print_panic_cost_table(&report, &crate_data.data, term_width),
+            MessageFormat::Json => return Some(panic_cost_json(&report, &crate_data.data)),
+        }
+    } else if args.by_object {
+        let objects = objects::collect(crate_data, &args.options);
+        match args.message_format {
+            MessageFormat::Table => print_objects_table(&objects, &crate_data.data, term_width),
+            MessageFormat::Json => return Some(objects_json(&objects, &crate_data.data)),
+        }
+    } else if args.by_source {
+        let sources = sources::collect(crate_data, &args.options);
+        match args.message_format {
+            MessageFormat::Table => print_sources_table(&sources, &crate_data.data, term_width),
+            MessageFormat::Json => return Some(sources_json(&sources, &crate_data.data)),
+        }
+    } else if args.crates {
+        let crates = cargo_bloat::filter_crates(crate_data, &args.options);
+        match args.message_format {
+            MessageFormat::Table => {
+                if args.no_relative_size {
+                    print_crates_table_no_relative(crates, &crate_data.data, term_width);
+                } else {
+                    print_crates_table(crates, &crate_data.data, term_width);
+                }
+            }
+            MessageFormat::Json => return Some(crates_json(&crates.crates, &crate_data.data)),
+        }
+    } else {
+        let methods = cargo_bloat::filter_methods(crate_data, &args.options);
+        if args.options.filter.is_some() && !methods.has_filter {
+            eprintln!(
+                "Warning: the filter value contains an unknown crate \
+                       or an invalid regexp. Ignored."
+            );
+        }
+
+        match args.message_format {
+            MessageFormat::Table => {
+                if args.no_relative_size {
+                    print_methods_table_no_relative(methods, &crate_data.data, term_width);
+                } else {
+                    print_methods_table(methods, &crate_data.data, term_width);
+                }
+            }
+            MessageFormat::Json => return Some(methods_json(&methods.methods, &crate_data.data)),
+        }
+    }
+
+    None
+}
+
+/// Prints JSON reports of all binaries along with their paths.
+///
+/// Reports are always wrapped into a `binaries` array,
+/// so the output has the same shape no matter how many binaries were built.
+fn print_json(reports: Vec<(Option<String>, json::JsonValue)>) {
+    let mut items = json::JsonValue::new_array();
+    for (path, report) in reports {
+        let mut item = json::JsonValue::new_object();
+        item["path"] = path.into();
+        for (key, value) in report.entries() {
+            item[key] = value.clone();
+        }
+        items.push(item).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["binaries"] = items;
+    println!("{}", root.dump());
+}
+
+fn print_methods_table(methods: Methods, data: &Data, term_width: Option<usize>) {
+    let section_name = data.section_name.as_deref().unwrap_or(".text");
+    let mut table = Table::new(&["File", section_name, "Size", "Crate", "Name"]);
+    table.set_width(term_width);
+
+    for method in &methods.methods {
+        table.push(&[
+            format_percent(method.size as f64 / data.file_size as f64 * 100.0),
+            format_percent(method.size as f64 / data.text_size as f64 * 100.0),
+            format_size(method.size),
+            method.crate_name.clone(),
+            method.name.clone(),
+        ]);
+    }
+
+    {
+        let others_count = if methods.has_filter {
+            methods.filter_out_len - methods.methods.len()
+        } else {
+            data.symbols.len() - methods.methods.len()
+        };
+
+        if others_count != 0 {
+            table.push(&[
+                format_percent(methods.filter_out_size as f64 / data.file_size as f64 * 100.0),
+                format_percent(methods.filter_out_size as f64 / data.text_size as f64 * 100.0),
+                format_size(methods.filter_out_size),
+                String::new(),
+                format!(
+                    "And {} smaller methods. Use -n N to show more.",
+                    others_count
+                ),
+            ]);
+        }
+    }
+
+    if methods.has_filter {
+        let total = methods.methods.iter().fold(0u64, |s, m| s + m.size) + methods.filter_out_size;
+
+        table.push(&[
+            format_percent(total as f64 / data.file_size as f64 * 100.0),
+            format_percent(total as f64 / data.text_size as f64 * 100.0),
+            format_size(total),
+            String::new(),
+            format!(
+                "filtered data size, the file size is {}",
+                format_size(data.file_size)
+            ),
+        ]);
+    } else {
+        table.push(&[
+            format_percent(data.text_size as f64 / data.file_size as f64 * 100.0),
+            format_percent(100.0),
+            format_size(data.text_size),
+            String::new(),
+            format!(
+                "{} section size, the file size is {}",
+                section_name,
+                format_size(data.file_size)
+            ),
+        ]);
+    }
+
+    print!("{}", table);
+}
+
+fn print_methods_table_no_relative(methods: Methods, data: &Data, term_width: Option<usize>) {
+    let mut table = Table::new(&["Size", "Crate", "Name"]);
+    table.set_width(term_width);
+
+    for method in &methods.methods {
+        table.push(&[
+            format_size(method.size),
+            method.crate_name.clone(),
+            method.name.clone(),
+        ]);
+    }
+
+    {
+        let others_count = if methods.has_filter {
+            methods.filter_out_len - methods.methods.len()
+        } else {
+            data.symbols.len() - methods.methods.len()
+        };
+
+        if others_count != 0 {
+            table.push(&[
+                format_size(methods.filter_out_size),
+                String::new(),
+                format!(
+                    "And {} smaller methods. Use -n N to show more.",
+                    others_count
+                ),
+            ]);
+        }
+    }
+
+    if methods.has_filter {
+        let total = methods.methods.iter().fold(0u64, |s, m| s + m.size) + methods.filter_out_size;
+
+        table.push(&[
+            format_size(total),
+            String::new(),
+            format!(
+                "filtered data size, the file size is {}",
+                format_size(data.file_size)
+            ),
+        ]);
+    } else {
+        table.push(&[
+            format_size(data.text_size),
+            String::new(),
+            format!(
+                ".text section size, the file size is {}",
+                format_size(data.file_size)
+            ),
+        ]);
+    }
+
+    print!("{}", table);
+}
+
+fn methods_json(methods: &[Method], data: &Data) -> json::JsonValue {
+    let mut items = json::JsonValue::new_array();
+    for method in methods {
+        let mut map = json::JsonValue::new_object();
+        if method.crate_name != cargo_bloat::UNKNOWN_CRATE {
+            map["crate"] = method.crate_name.clone().into();
+        }
+        map["name"] = method.name.clone().into();
+        map["size"] = method.size.into();
+
+        items.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["file-size"] = data.file_size.into();
+    root["text-section-size"] = data.text_size.into();
+    if let Some(ref memory) = data.memory {
+        root["flash-size"] = memory.flash_size.into();
+        root["ram-size"] = memory.ram_size.into();
+    }
+    root["functions"] = items;
+
+    root
+}
+
+fn print_crates_table(crates: Crates, data: &Data, term_width: Option<usize>) {
+    let section_name = data.section_name.as_deref().unwrap_or(".text");
+    let mut table = Table::new(&["File", section_name, "Size", "Crate"]);
+    table.set_width(term_width);
+
+    for item in &crates.crates {
+        table.push(&[
+            format_percent(item.size as f64 / data.file_size as f64 * 100.0),
+            format_percent(item.size as f64 / data.text_size as f64 * 100.0),
+            format_size(item.size),
+            item.name.clone(),
+        ]);
+    }
+
+    if crates.filter_out_len != 0 {
+        table.push(&[
+            format_percent(crates.filter_out_size as f64 / data.file_size as f64 * 100.0),
+            format_percent(crates.filter_out_size as f64 / data.text_size as f64 * 100.0),
+            format_size(crates.filter_out_size),
+            format!(
+                "And {} more crates. Use -n N to show more.",
+                crates.filter_out_len
+            ),
+        ]);
+    }
+
+    table.push(&[
+        format_percent(data.text_size as f64 / data.file_size as f64 * 100.0),
+        format_percent(100.0),
+        format_size(data.text_size),
+        format!(
+            "{} section size, the file size is {}",
+            section_name,
+            format_size(data.file_size)
+        ),
+    ]);
+
+    print!("{}", table);
+}
+
+fn print_crates_table_no_relative(crates: Crates, data: &Data, term_width: Option<usize>) {
+    let mut table = Table::new(&["Size", "Crate"]);
+    table.set_width(term_width);
+
+    for item in &crates.crates {
+        table.push(&[format_size(item.size), item.name.clone()]);
+    }
+
+    if crates.filter_out_len != 0 {
+        table.push(&[
+            format_size(crates.filter_out_size),
+            format!(
+                "And {} more crates. Use -n N to show more.",
+                crates.filter_out_len
+            ),
+        ]);
+    }
+
+    let section_name = data.section_name.as_deref().unwrap_or(".text");
+    table.push(&[
+        format_size(data.text_size),
+        format!(
+            "{} section size, the file size is {}",
+            section_name,
+            format_size(data.file_size)
+        ),
+    ]);
+
+    print!("{}", table);
+}
+
+fn crates_json(crates: &[Crate], data: &Data) -> json::JsonValue {
+    let mut items = json::JsonValue::new_array();
+    for item in crates {
+        let mut map = json::JsonValue::new_object();
+        map["name"] = item.name.clone().into();
+        map["size"] = item.size.into();
+
+        items.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["file-size"] = data.file_size.into();
+    root["text-section-size"] = data.text_size.into();
+    if let Some(ref memory) = data.memory {
+        root["flash-size"] = memory.flash_size.into();
+        root["ram-size"] = memory.ram_size.into();
+    }
+    root["crates"] = items;
+
+    root
+}
+
+/// Strips directories from an object path, including a library member one.
+fn short_name(file: &str) -> String {
+    let file_name = |path: &str| path.rsplit(['/', '\\']).next().unwrap_or(path).to_string();
+
+    match (file.find('('), file.strip_suffix(')')) {
+        (Some(idx), Some(file)) => {
+            format!(
+                "{}({})",
+                file_name(&file[..idx]),
+                file_name(&file[idx + 1..])
+            )
+        }
+        _ => file_name(file),
+    }
+}
+
+fn print_objects_table(objects: &Objects, data: &Data, term_width: Option<usize>) {
+    let section_name = data.section_name.as_deref().unwrap_or(".text");
+    let mut table = Table::new(&["File", section_name, "Size", "Crate", "Object"]);
+    table.set_width(term_width);
+
+    for item in &objects.objects {
+        table.push(&[
+            format_percent(item.size as f64 / data.file_size as f64 * 100.0),
+            format_percent(item.size as f64 / data.text_size as f64 * 100.0),
+            format_size(item.size),
+            item.crate_name.clone(),
+            short_name(&item.file),
+        ]);
+    }
+
+    if objects.filter_out_len != 0 {
+        table.push(&[
+            format_percent(objects.filter_out_size as f64 / data.file_size as f64 * 100.0),
+            format_percent(objects.filter_out_size as f64 / data.text_size as f64 * 100.0),
+            format_size(objects.filter_out_size),
+            String::new(),
+            format!(
+                "And {} more objects. Use -n N to show more.",
+                objects.filter_out_len
+            ),
+        ]);
+    }
+
+    table.push(&[
+        format_percent(data.text_size as f64 / data.file_size as f64 * 100.0),
+        format_percent(100.0),
+        format_size(data.text_size),
+        String::new(),
+        format!(
+            "{} section size, the file size is {}",
+            section_name,
+            format_size(data.file_size)
+        ),
+    ]);
+
+    print!("{}", table);
+
+    if objects.is_empty {
+        println!();
+        println!(
+            "Warning: object files are known only for PDB files and linker maps. \
+             Try --emit-linker-map."
+        );
+    }
+}
+
+fn objects_json(objects: &Objects, data: &Data) -> json::JsonValue {
+    let mut items = json::JsonValue::new_array();
+    for item in &objects.objects {
+        let mut map = json::JsonValue::new_object();
+        map["name"] = item.file.clone().into();
+        map["crate"] = item.crate_name.clone().into();
+        map["size"] = item.size.into();
+        items.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["file-size"] = data.file_size.into();
+    root["text-section-size"] = data.text_size.into();
+    root["objects"] = items;
+
+    root
+}
+
+fn print_sources_table(sources: &Sources, data: &Data, term_width: Option<usize>) {
+    let section_name = data.section_name.as_deref().unwrap_or(".text");
+    let mut table = Table::new(&["File", section_name, "Size", "Inlined", "Source"]);
+    table.set_width(term_width);
+
+    for item in &sources.files {
+        table.push(&[
+            format_percent(item.size as f64 / data.file_size as f64 * 100.0),
+            format_percent(item.size as f64 / data.text_size as f64 * 100.0),
+            format_size(item.size),
+            format_size(item.inlined),
+            item.name.clone(),
+        ]);
+    }
+
+    if sources.filter_out_len != 0 {
+        table.push(&[
+            format_percent(sources.filter_out_size as f64 / data.file_size as f64 * 100.0),
+            format_percent(sources.filter_out_size as f64 / data.text_size as f64 * 100.0),
+            format_size(sources.filter_out_size),
+            format_size(sources.filter_out_inlined),
+            format!(
+                "And {} more files. Use -n N to show more.",
+                sources.filter_out_len
+            ),
+        ]);
+    }
+
+    table.push(&[
+        format_percent(data.text_size as f64 / data.file_size as f64 * 100.0),
+        format_percent(100.0),
+        format_size(data.text_size),
+        String::new(),
+        format!(
+            "{} section size, the file size is {}",
+            section_name,
+            format_size(data.file_size)
+        ),
+    ]);
+
+    print!("{}", table);
+
+    if data.source_files.is_empty() {
+        println!();
+        println!("Warning: source files are known only for PDB files.");
+    }
+}
+
+fn sources_json(sources: &Sources, data: &Data) -> json::JsonValue {
+    let mut items = json::JsonValue::new_array();
+    for item in &sources.files {
+        let mut map = json::JsonValue::new_object();
+        map["name"] = item.name.clone().into();
+        map["size"] = item.size.into();
+        map["inlined"] = item.inlined.into();
+        items.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["file-size"] = data.file_size.into();
+    root["text-section-size"] = data.text_size.into();
+    root["source-files"] = items;
+
+    root
+}
+
+fn print_panic_cost_table(report: &Report, data: &Data, term_width: Option<usize>) {
+    let section_name = data.section_name.as_deref().unwrap_or(".text");
+    let mut table = Table::new(&["File", section_name, "Size", "Name"]);
+    table.set_width(term_width);
+
+    let mut total = 0;
+    for &(category, size, count) in &report.categories {
+        total += size;
+        table.push(&[
+            format_percent(size as f64 / data.file_size as f64 * 100.0),
+            format_percent(size as f64 / data.text_size as f64 * 100.0),
+            format_size(size),
+            format!("{} ({} functions)", category.name(), count),
+        ]);
+    }
+
+    for (name, size) in &report.sections {
+        // Unwinding tables are not a part of the machinery that could be removed.
+        let kept = panic_cost::is_kept_with_abort(name);
+        if !kept {
+            total += size;
+        }
+
+        table.push(&[
+            format_percent(*size as f64 / data.file_size as f64 * 100.0),
+            String::new(),
+            format_size(*size),
+            if kept {
+                format!("{} section (kept with panic = \"abort\")", name)
+            } else {
+                format!("{} section", name)
+            },
+        ]);
+    }
+
+    table.push(&[
+        format_percent(total as f64 / data.file_size as f64 * 100.0),
+        String::new(),
+        format_size(total),
+        format!(
+            "panic and formatting machinery, the file size is {}",
+            format_size(data.file_size)
+        ),
+    ]);
+
+    print!("{}", table);
+
+    println!();
+    println!("Estimated savings:");
+    let mut table = Table::new(&["Size", "Setting"]);
+    table.push(&[
+        format_size(report.abort_savings),
+        "panic = \"abort\" (at least, landing pads are not counted)".to_string(),
+    ]);
+    table.push(&[
+        format_size(report.immediate_abort_savings),
+        "panic_immediate_abort (at most, requires build-std)".to_string(),
+    ]);
+    print!("{}", table);
+}
+
+fn panic_cost_json(report: &Report, data: &Data) -> json::JsonValue {
+    let mut categories = json::JsonValue::new_array();
+    for &(category, size, count) in &report.categories {
+        let mut map = json::JsonValue::new_object();
+        map["name"] = category.json_name().into();
+        map["size"] = size.into();
+        map["functions"] = count.into();
+        categories.push(map).unwrap();
+    }
+
+    let mut sections = json::JsonValue::new_array();
+    for (name, size) in &report.sections {
+        let mut map = json::JsonValue::new_object();
+        map["name"] = name.clone().into();
+        map["size"] = (*size).into();
+        map["kept-with-abort"] = panic_cost::is_kept_with_abort(name).into();
+        sections.push(map).unwrap();
+    }
+
+    let mut root = json::JsonValue::new_object();
+    root["file-size"] = data.file_size.into();
+    root["text-section-size"] = data.text_size.into();
+    root["categories"] = categories;
+    root["sections"] = sections;
+    root["panic-abort-savings"] = report.abort_savings.into();
+    root["panic-immediate-abort-savings"] = report.immediate_abort_savings.into();
+
+    root
+}
+
+fn print_memory_table(memory: &Memory, n: usize, term_width: Option<usize>) {
+    let mut table = Table::new(&["Address", "Size", "Flash", "RAM", "Section"]);
+    table.set_width(term_width);
+
+    let yes_no = |v: bool| if v { "yes" } else { "" }.to_string();
+    for section in &memory.sections {
+        table.push(&[
+            format!("{:#010x}", section.address),
+            format_size(section.size),
+            yes_no(section.in_flash),
+            yes_no(section.in_ram),
+            section.name.clone(),
+        ]);
+    }
+    print!("{}", table);
+
+    println!();
+    println!(
+        "Flash: {}, RAM: {}",
+        format_size(memory.flash_size),
+        format_size(memory.ram_size)
+    );
+
+    if !memory.objects.is_empty() {
+        println!();
+
+        let mut table = Table::new(&["Flash", "RAM", "Crate", "Object"]);
+        table.set_width(term_width);
+
+        let n = if n == 0 { memory.objects.len() } else { n };
+        for object in memory.objects.iter().take(n) {
+            table.push(&[
+                format_size(object.flash_size),
+                format_size(object.ram_size),
+                object.crate_name.clone(),
+                object
+                    .file
+                    .rsplit(['/', '\\'])
+                    .next()
+                    .unwrap_or(&object.file)
+                    .to_string(),
+            ]);
+        }
+
+        let others = memory.objects.len().saturating_sub(n);
+        if others != 0 {
+            let rest = memory.objects.iter().skip(n);
+            table.push(&[
+                format_size(rest.clone().map(|o| o.flash_size).sum()),
+                format_size(rest.map(|o| o.ram_size).sum()),
+                String::new(),
+                format!("And {} more objects. Use -n N to show more.", others),
+            ]);
+        }
+
+        print!("{}", table);
+    }
+
+    println!();
+}
+
+pub fn format_percent(n: f64) -> String {
+    format!("{:.1}%", n)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let kib = 1024;
+    let mib = 1024 * kib;
+
+    if bytes >= mib {
+        format!("{:.1}MiB", bytes as f64 / mib as f64)
+    } else if bytes >= kib {
+        format!("{:.1}KiB", bytes as f64 / kib as f64)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+pub fn format_size_diff(diff: i64) -> String {
+    if diff < 0 {
+        format!("-{}", format_size(diff.unsigned_abs()))
+    } else {
+        format!("+{}", format_size(diff as u64))
+    }
+}
@@ -45,7 +45,7 @@ impl fmt::Display for Table {
         for col in 0..self.columns_count {
             let mut max = 0;
             for row in 0..rows {
-                max = cmp::max(max, self.cell(row, col).len());
+                max = cmp::max(max, self.cell(row, col).chars().count());
             }
             max_len_list.push(max);
         }
@@ -61,7 +61,7 @@ impl fmt::Display for Table {
 
             for (col, cell) in row_data.iter().enumerate() {
                 if col != self.columns_count - 1 {
-                    let pad = max_len_list[col] - cell.len();
+                    let pad = max_len_list[col] - cell.chars().count();
                     for _ in 0..pad {
                         write!(f, " ")?;
                     }
@@ -72,8 +72,8 @@ impl fmt::Display for Table {
                         let name_width = w - non_fn_width;
 
                         let mut cell = cell.clone();
-                        if cell.len() > name_width {
-                            cell.drain((name_width - 3)..);
+                        if cell.chars().count() > name_width {
+                            cell = cell.chars().take(name_width - 3).collect();
                             cell.push_str("...");
                         }
                         writeln!(f, "{}", cell)?;
@@ -0,0 +1,549 @@
+//! Interactive terminal UI.
+//!
+//! The analysis is done once, so switching views, filtering and sorting are instant.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::panic;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+
+use crate::table::Table;
+use cargo_bloat::{symbol_crates, CrateData, Error, Options};
+
+use crate::print::{format_percent, format_size};
+use crate::Args;
+
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Crates,
+    Modules,
+    Functions,
+}
+
+impl View {
+    fn title(self) -> &'static str {
+        match self {
+            View::Crates => "Crates",
+            View::Modules => "Modules",
+            View::Functions => "Functions",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            View::Crates => View::Modules,
+            View::Modules => View::Functions,
+            View::Functions => View::Crates,
+        }
+    }
+}
+
+/// A crate or a module the functions view is limited to.
+struct Scope {
+    name: String,
+    is_crate: bool,
+    /// A view to return to.
+    prev_view: View,
+}
+
+struct Row {
+    size: u64,
+    crate_name: String,
+    name: String,
+}
+
+enum Matcher {
+    All,
+    #[cfg(feature = "regex-filter")]
+    Regex(regex::Regex),
+    Substring(String),
+}
+
+impl Matcher {
+    fn is_match(&self, text: &str) -> bool {
+        match *self {
+            Matcher::All => true,
+            #[cfg(feature = "regex-filter")]
+            Matcher::Regex(ref re) => re.is_match(text),
+            Matcher::Substring(ref s) => text.contains(s.as_str()),
+        }
+    }
+}
+
+struct App<'a> {
+    crates: &'a [CrateData],
+    binary: usize,
+    options: Options,
+    /// Crate names of all symbols, with std crates split, and whether they're exact.
+    symbol_crates: Vec<(String, bool)>,
+    view: View,
+    scope: Option<Scope>,
+    filter: String,
+    /// The filter is not a valid regex and is matched as a plain text.
+    filter_is_text: bool,
+    editing_filter: bool,
+    sort_by_name: bool,
+    rows: Vec<Row>,
+    selected: usize,
+    offset: usize,
+    page: usize,
+}
+
+/// Shows the results in an interactive UI until the user quits.
+pub fn run(crates: &[CrateData], args: &Args) -> Result<(), Error> {
+    let mut app = App {
+        crates,
+        binary: 0,
+        options: args.options.clone(),
+        symbol_crates: Vec::new(),
+        view: if args.crates {
+            View::Crates
+        } else {
+            View::Functions
+        },
+        scope: None,
+        filter: args.options.filter.clone().unwrap_or_default(),
+        filter_is_text: false,
+        editing_filter: false,
+        sort_by_name: false,
+        rows: Vec::new(),
+        selected: 0,
+        offset: 0,
+        page: 1,
+    };
+    app.load_binary(0);
+
+    let _guard = TerminalGuard::new().map_err(Error::TuiFailed)?;
+    app.event_loop(&mut io::stdout()).map_err(Error::TuiFailed)
+}
+
+/// Switches the terminal to the raw mode and the alternate screen.
+///
+/// Restores it on drop, so the shell stays usable after an error or a panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        // Restore the terminal before a panic message is printed,
+        // otherwise it would end up on the alternate screen and disappear.
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            hook(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn restore_terminal() {
+    let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+impl App<'_> {
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<()> {
+        loop {
+            self.draw(out)?;
+
+            // Any other event, like a resize, simply redraws the screen.
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn load_binary(&mut self, idx: usize) {
+        self.binary = idx;
+        self.scope = None;
+
+        // Names are resolved with std split once, so toggling `--split-std` is instant.
+        let d = &self.crates[idx];
+        let mut options = self.options.clone();
+        options.split_std = true;
+        self.symbol_crates = symbol_crates(d, &options);
+
+        self.update_rows();
+    }
+
+    fn crate_name(&self, idx: usize) -> (&str, bool) {
+        let (ref name, is_exact) = self.symbol_crates[idx];
+        if !self.options.split_std && self.crates[self.binary].std_crates.contains(name) {
+            ("std", is_exact)
+        } else {
+            (name, is_exact)
+        }
+    }
+
+    fn matcher(&mut self) -> Matcher {
+        self.filter_is_text = false;
+        if self.filter.is_empty() {
+            return Matcher::All;
+        }
+
+        #[cfg(feature = "regex-filter")]
+        {
+            if let Ok(re) = regex::Regex::new(&self.filter) {
+                return Matcher::Regex(re);
+            }
+
+            self.filter_is_text = true;
+        }
+
+        Matcher::Substring(self.filter.clone())
+    }
+
+    fn update_rows(&mut self) {
+        let matcher = self.matcher();
+        let d = &self.crates[self.binary];
+
+        let mut rows = Vec::new();
+        let mut groups: HashMap<String, (u64, &str)> = HashMap::new();
+        for (idx, sym) in d.data.symbols.iter().enumerate() {
+            let (crate_name, is_exact) = self.crate_name(idx);
+            if let Some(ref scope) = self.scope {
+                let name = if scope.is_crate {
+                    crate_name.to_string()
+                } else {
+                    module_of(&sym.name.trimmed)
+                };
+
+                if name != scope.name {
+                    continue;
+                }
+            }
+
+            match self.view {
+                View::Functions => {
+                    let name = if self.options.full_fn {
+                        &sym.name.complete
+                    } else {
+                        &sym.name.trimmed
+                    };
+
+                    if matcher.is_match(name) {
+                        let mut crate_name = crate_name.to_string();
+                        if !is_exact {
+                            crate_name.push('?');
+                        }
+
+                        rows.push(Row {
+                            size: sym.size,
+                            crate_name,
+                            name: name.clone(),
+                        });
+                    }
+                }
+                View::Crates => {
+                    groups
+                        .entry(crate_name.to_string())
+                        .or_insert((0, crate_name))
+                        .0 += sym.size;
+                }
+                View::Modules => {
+                    let module = module_of(&sym.name.trimmed);
+                    groups.entry(module).or_insert((0, crate_name)).0 += sym.size;
+                }
+            }
+        }
+
+        for (name, (size, crate_name)) in groups {
+            if matcher.is_match(&name) {
+                rows.push(Row {
+                    size,
+                    crate_name: crate_name.to_string(),
+                    name,
+                });
+            }
+        }
+
+        if self.sort_by_name {
+            rows.sort_by(|a, b| a.name.cmp(&b.name).then(b.size.cmp(&a.size)));
+        } else {
+            rows.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+        }
+
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Returns `false` when the user wants to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                _ => return true,
+            }
+
+            self.selected = 0;
+            self.update_rows();
+            return true;
+        }
+
+        let last = self.rows.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(self.page),
+            KeyCode::PageDown => self.selected = (self.selected + self.page).min(last),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = last,
+            KeyCode::Tab => self.set_view(self.view.next()),
+            KeyCode::Char('1') => self.set_view(View::Crates),
+            KeyCode::Char('2') => self.set_view(View::Modules),
+            KeyCode::Char('3') => self.set_view(View::Functions),
+            KeyCode::Enter | KeyCode::Right => self.open_selected(),
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Left => {
+                if let Some(scope) = self.scope.take() {
+                    self.set_view(scope.prev_view);
+                }
+            }
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('s') => {
+                self.sort_by_name = !self.sort_by_name;
+                self.update_rows();
+            }
+            KeyCode::Char('f') => {
+                self.options.full_fn = !self.options.full_fn;
+                self.update_rows();
+            }
+            KeyCode::Char('t') => {
+                self.options.split_std = !self.options.split_std;
+                self.update_rows();
+            }
+            KeyCode::Char('b') => {
+                self.selected = 0;
+                self.load_binary((self.binary + 1) % self.crates.len());
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    fn set_view(&mut self, view: View) {
+        self.view = view;
+        self.selected = 0;
+        self.update_rows();
+    }
+
+    /// Shows functions of the selected crate or module.
+    fn open_selected(&mut self) {
+        if self.view == View::Functions || self.rows.is_empty() {
+            return;
+        }
+
+        self.scope = Some(Scope {
+            name: self.rows[self.selected].name.clone(),
+            is_crate: self.view == View::Crates,
+            prev_view: self.view,
+        });
+        self.filter.clear();
+        self.set_view(View::Functions);
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+
+        // The title, the table header and the status line.
+        self.page = height.saturating_sub(3).max(1);
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + self.page {
+            self.offset = self.selected + 1 - self.page;
+        }
+
+        let d = &self.crates[self.binary];
+        let section_name = d.data.section_name.as_deref().unwrap_or(".text");
+        let mut table = match self.view {
+            View::Crates => Table::new(&["File", section_name, "Size", "Crate"]),
+            View::Modules => Table::new(&["File", section_name, "Size", "Crate", "Module"]),
+            View::Functions => Table::new(&["File", section_name, "Size", "Crate", "Name"]),
+        };
+        // The table expects a reasonable width, while lines are cut to the screen anyway.
+        table.set_width(Some(width.max(80)));
+
+        for row in self.rows.iter().skip(self.offset).take(self.page) {
+            let mut cells = vec![
+                format_percent(row.size as f64 / d.data.file_size as f64 * 100.0),
+                format_percent(row.size as f64 / d.data.text_size as f64 * 100.0),
+                format_size(row.size),
+                row.crate_name.clone(),
+            ];
+            if self.view != View::Crates {
+                cells.push(row.name.clone());
+            }
+            table.push(&cells);
+        }
+
+        let mut lines = vec![self.title()];
+        lines.extend(table.to_string().lines().map(str::to_string));
+        lines.resize(height.saturating_sub(1).max(lines.len()), String::new());
+        lines.push(self.status());
+
+        for (y, line) in lines.iter().enumerate().take(height) {
+            let mut line: String = line.chars().take(width).collect();
+            queue!(
+                out,
+                cursor::MoveTo(0, y as u16),
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+
+            let row = (y + self.offset).checked_sub(2);
+            if y >= 2 && row == Some(self.selected) && self.selected < self.rows.len() {
+                let len = line.chars().count();
+                line.extend(std::iter::repeat_n(' ', width - len));
+                queue!(
+                    out,
+                    SetAttribute(Attribute::Reverse),
+                    Print(line),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(out, Print(line))?;
+            }
+        }
+
+        out.flush()
+    }
+
+    fn title(&self) -> String {
+        let d = &self.crates[self.binary];
+        let mut parts = vec![
+            d.exe_path.clone().unwrap_or_default(),
+            self.view.title().to_string(),
+        ];
+
+        if let Some(ref scope) = self.scope {
+            let kind = if scope.is_crate { "crate" } else { "module" };
+            parts.push(format!("{}: {}", kind, scope.name));
+        }
+
+        if !self.filter.is_empty() {
+            let suffix = if self.filter_is_text {
+                " (as text)"
+            } else {
+                ""
+            };
+            parts.push(format!("filter: {}{}", self.filter, suffix));
+        }
+
+        parts.push(format!("{} rows", self.rows.len()));
+        parts.push(format!(
+            "sort: {}",
+            if self.sort_by_name { "name" } else { "size" }
+        ));
+        if self.options.full_fn {
+            parts.push("full-fn".to_string());
+        }
+        if self.options.split_std {
+            parts.push("split-std".to_string());
+        }
+
+        parts.join(" | ")
+    }
+
+    fn status(&self) -> String {
+        if self.editing_filter {
+            return format!("Filter: {}_", self.filter);
+        }
+
+        let mut help = "Tab/1-3: view  Enter: open  Esc: back  /: filter  s: sort  \
+                        f: full-fn  t: split-std"
+            .to_string();
+        if self.crates.len() > 1 {
+            help.push_str("  b: next binary");
+        }
+        help.push_str("  q: quit");
+        help
+    }
+}
+
+/// Returns a module of a function, like `core::fmt` for `core::fmt::write`.
+///
+/// Like crate names, this is a guess based on the demangled name.
+/// Methods belong to the module of their type, and type names are omitted.
+fn module_of(name: &str) -> String {
+    // `<T as Trait>::method` and `<T>::method` belong to the module of `T`.
+    let path = match name.strip_prefix('<') {
+        Some(inner) => {
+            let mut depth = 1;
+            let end = inner
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map_or(inner.len(), |(idx, _)| idx);
+            let inner = &inner[..end];
+            inner.split(" as ").next().unwrap_or(inner)
+        }
+        None => name,
+    };
+
+    // Remove generic arguments.
+    let mut plain = String::with_capacity(path.len());
+    let mut depth = 0;
+    for c in path.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ if depth == 0 => plain.push(c),
+            _ => {}
+        }
+    }
+
+    let mut parts: Vec<&str> = plain
+        .split("::")
+        .filter(|p| !p.is_empty() && !p.starts_with('{'))
+        .collect();
+
+    // A function itself or a type of a method.
+    parts.pop();
+    while parts.len() > 1
+        && parts
+            .last()
+            .is_some_and(|p| p.starts_with(char::is_uppercase))
+    {
+        parts.pop();
+    }
+
+    if parts.is_empty() {
+        plain
+    } else {
+        parts.join("::")
+    }
+}
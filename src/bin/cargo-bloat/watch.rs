@@ -0,0 +1,223 @@
+//! Watch mode.
+//!
+//! Source files are polled for changes, since the analysis takes way longer
+//! than checking a few hundred timestamps.
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::time::{Duration, SystemTime};
+use std::{fs, path, thread};
+
+use cargo_bloat::{build, cargo_command, Error, Options};
+
+use crate::compare::{self, Comparison, Entry};
+use crate::print::{format_size_diff, print_binaries, print_warnings};
+use crate::{Args, MessageFormat};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Number of the most changed crates and functions to show after a rebuild.
+const TOP_CHANGES: usize = 10;
+
+type Snapshot = HashMap<path::PathBuf, SystemTime>;
+
+/// Files and directories that trigger a rebuild.
+struct Watched {
+    /// Directories with sources of workspace packages and their manifests.
+    dirs: Vec<path::PathBuf>,
+    /// Workspace-wide files, like the root manifest of a virtual workspace.
+    files: Vec<path::PathBuf>,
+    /// The target directory, which is usually inside of a package directory.
+    target_dir: Option<path::PathBuf>,
+}
+
+/// Rebuilds and analyzes the crate each time its sources are changed.
+///
+/// Never returns unless the list of the watched directories cannot be collected.
+pub fn run(args: &Args, workspace_root: &str, term_width: Option<usize>) -> Result<(), Error> {
+    let watched = watched(&args.options)?;
+    let mut snapshot = scan(&watched);
+    let mut prev: Option<Vec<Entry>> = None;
+    loop {
+        if args.message_format == MessageFormat::Table && std::io::stdout().is_terminal() {
+            // Clear the screen and move the cursor to the top.
+            print!("\x1b[2J\x1b[H");
+        }
+
+        match build(&args.options, workspace_root) {
+            Ok(mut crates) => {
+                print_warnings(&crates);
+                print_binaries(&mut crates, args, term_width);
+
+                let entries: Vec<Entry> = crates.iter().map(|d| Entry::new(d, args)).collect();
+                prev = Some(match prev.take() {
+                    Some(prev) => {
+                        let comparisons = compare::compare(prev, entries);
+                        if args.message_format == MessageFormat::Table {
+                            print_changes(&comparisons);
+                        }
+
+                        comparisons.into_iter().filter_map(|c| c.head).collect()
+                    }
+                    None => entries,
+                });
+            }
+            // Errors are not fatal here, but still have to be readable by tools.
+            Err(e) => match args.message_format {
+                MessageFormat::Table => eprintln!("Error: {}.", e),
+                MessageFormat::Json => println!("{}", e.to_json().dump()),
+            },
+        }
+
+        eprintln!();
+        eprintln!("    Watching for changes...");
+
+        snapshot = wait_for_changes(&watched, snapshot);
+    }
+}
+
+fn print_changes(comparisons: &[Comparison]) {
+    for c in comparisons {
+        let (base, head) = match (&c.base, &c.head) {
+            (Some(base), Some(head)) => (base, head),
+            _ => continue,
+        };
+
+        if base.file_size == head.file_size && base.text_size == head.text_size {
+            continue;
+        }
+
+        println!();
+        println!(
+            "Changes in {} since the previous build: file size {}, .text {}.",
+            c.name,
+            format_size_diff(head.file_size as i64 - base.file_size as i64),
+            format_size_diff(head.text_size as i64 - base.text_size as i64),
+        );
+        compare::print_changes(c, TOP_CHANGES);
+    }
+}
+
+/// Returns workspace packages directories, workspace-wide files and the target directory.
+fn watched(options: &Options) -> Result<Watched, Error> {
+    let mut cmd = cargo_command(options);
+    cmd.args(["metadata", "--format-version=1", "--no-deps"]);
+    if let Some(ref path) = options.manifest_path {
+        cmd.arg(format!("--manifest-path={}", path));
+    }
+
+    // `cargo metadata` doesn't have `--target-dir`, but resolves the environment variable.
+    if let Some(ref dir) = options.target_dir {
+        cmd.env("CARGO_TARGET_DIR", dir);
+    }
+
+    let output = cmd.output().map_err(Error::CargoFailed)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let msg = stderr.trim();
+        return Err(Error::CargoError(
+            msg.strip_prefix("error: ").unwrap_or(msg).to_string(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next().unwrap_or("");
+    let meta = json::parse(line)
+        .map_err(|e| Error::InvalidCargoOutput(line.to_string(), e.to_string()))?;
+
+    let mut dirs: Vec<path::PathBuf> = Vec::new();
+    for package in meta["packages"].members() {
+        let manifest_dir = package["manifest_path"].as_str().map(path::Path::new);
+        let target_dirs = package["targets"]
+            .members()
+            .filter_map(|t| t["src_path"].as_str())
+            .map(path::Path::new);
+
+        for dir in manifest_dir
+            .into_iter()
+            .chain(target_dirs)
+            .filter_map(|p| p.parent())
+        {
+            if !dirs.iter().any(|d| dir.starts_with(d)) {
+                dirs.retain(|d| !d.starts_with(dir));
+                dirs.push(dir.to_owned());
+            }
+        }
+    }
+
+    // A virtual workspace root is not a package directory,
+    // and `.cargo` is skipped as a hidden directory anyway.
+    let files = match meta["workspace_root"].as_str() {
+        Some(root) => {
+            let root = path::Path::new(root);
+            vec![
+                root.join("Cargo.toml"),
+                root.join("Cargo.lock"),
+                root.join(".cargo").join("config.toml"),
+                root.join(".cargo").join("config"),
+            ]
+        }
+        None => Vec::new(),
+    };
+
+    Ok(Watched {
+        dirs,
+        files,
+        target_dir: meta["target_directory"].as_str().map(path::PathBuf::from),
+    })
+}
+
+/// Collects modification times of Rust sources, manifests and workspace-wide files.
+///
+/// Hidden directories and the target directory are skipped, since a manifest directory
+/// usually contains the build output as well.
+fn scan(watched: &Watched) -> Snapshot {
+    fn scan_dir(dir: &path::Path, watched: &Watched, snapshot: &mut Snapshot) {
+        let entries = match fs::read_dir(dir) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') && watched.target_dir.as_deref() != Some(path.as_path()) {
+                    scan_dir(&path, watched, snapshot);
+                }
+            } else if name.ends_with(".rs") || name == "Cargo.toml" || name == "Cargo.lock" {
+                if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                    snapshot.insert(path, modified);
+                }
+            }
+        }
+    }
+
+    let mut snapshot = Snapshot::new();
+    for dir in &watched.dirs {
+        scan_dir(dir, watched, &mut snapshot);
+    }
+
+    for path in &watched.files {
+        if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+            snapshot.insert(path.clone(), modified);
+        }
+    }
+
+    snapshot
+}
+
+/// Blocks until a file is changed, added or removed, and returns the new state.
+fn wait_for_changes(watched: &Watched, snapshot: Snapshot) -> Snapshot {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let new = scan(watched);
+        if new != snapshot {
+            // Editors often write files in multiple steps, so wait until they are done.
+            thread::sleep(POLL_INTERVAL);
+            return scan(watched);
+        }
+    }
+}
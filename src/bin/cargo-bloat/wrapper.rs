@@ -0,0 +1,77 @@
+//! The `RUSTC_WRAPPER` mode, which measures how long each crate takes to compile.
+
+use std::convert::TryInto;
+use std::process::Command;
+
+use json::object;
+
+use cargo_bloat::Error;
+
+/// Runs `rustc` with the specified arguments and prints the compilation time to stderr.
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let start = std::time::Instant::now();
+
+    Command::new(&args[1])
+        .args(&args[2..])
+        .status()
+        .map_err(Error::RustcFailed)?;
+
+    let time_ns: u64 = start.elapsed().as_nanos().try_into()?;
+
+    let mut crate_name = String::new();
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--crate-name" {
+            crate_name = args[i + 1].clone();
+            break;
+        }
+    }
+
+    let mut build_script = false;
+
+    if crate_name == "build_script_build" {
+        build_script = true;
+
+        let mut out_dir = String::new();
+        let mut extra_filename = String::new();
+
+        for (i, arg) in args.iter().enumerate() {
+            if arg == "--out-dir" {
+                out_dir = args[i + 1].clone();
+            }
+
+            if arg.starts_with("extra-filename") {
+                extra_filename = arg[15..].to_string();
+            }
+        }
+
+        if !out_dir.is_empty() {
+            let path = std::path::Path::new(&out_dir);
+            if let Some(name) = path.file_name() {
+                let name = name.to_string_lossy().into_owned();
+                let name = name.replace(&extra_filename, "");
+                let name = name.replace('-', "_");
+                crate_name = name;
+            }
+        }
+    }
+
+    // Still not resolved?
+    if crate_name == "build_script_build" {
+        crate_name = "?".to_string();
+    }
+
+    // TODO: the same crates but with different versions?
+
+    // `cargo` will ignore raw JSON, so we have to use a prefix
+    eprintln!(
+        "json-time {}",
+        object! {
+            "crate_name" => crate_name,
+            "time" => time_ns,
+            "build_script" => build_script
+        }
+        .dump()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,332 @@
+use std::ffi::OsString;
+use std::{fs, path};
+
+pub const FILE_NAME: &str = "bloat.toml";
+
+/// Loads the `bloat.toml` file as a JSON object.
+///
+/// Returns `Ok(None)` when the file does not exist.
+pub fn load_file(path: &path::Path) -> Result<Option<json::JsonValue>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("failed to read '{}' cause '{}'", path.display(), e)),
+    };
+
+    parse_toml(&text)
+        .map(Some)
+        .map_err(|e| format!("failed to parse '{}' cause '{}'", path.display(), e))
+}
+
+/// Parses a flat TOML table.
+///
+/// Only `key = value` pairs are supported, where the value is a boolean, an integer,
+/// a string or a single-line array of those. This is all we need for the options.
+/// Tables are rejected instead of being merged into the top level silently.
+fn parse_toml(text: &str) -> Result<json::JsonValue, String> {
+    let mut table = json::JsonValue::new_object();
+    for (idx, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            return Err(format!(
+                "tables are not supported, but '{}' is set at line {}",
+                line,
+                idx + 1
+            ));
+        }
+
+        let (key, value) = match split_key_value(line) {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(format!("expected 'key = value' at line {}", idx + 1)),
+        };
+
+        let key = key.trim_matches(|c| c == '"' || c == '\'');
+        if key.is_empty() {
+            return Err(format!("missing key at line {}", idx + 1));
+        }
+
+        let value = parse_value(value).ok_or(format!("invalid value at line {}", idx + 1))?;
+        table[key] = value;
+    }
+
+    Ok(table)
+}
+
+/// Tracks whether characters are inside of a string, skipping escaped quotes.
+#[derive(Default)]
+pub(crate) struct Quotes {
+    quote: Option<char>,
+    escaped: bool,
+}
+
+impl Quotes {
+    /// Returns `true` when `c` is a part of a string, including its quotes.
+    pub(crate) fn is_quoted(&mut self, c: char) -> bool {
+        match self.quote {
+            None if c == '"' || c == '\'' => self.quote = Some(c),
+            None => return false,
+            Some(_) if self.escaped => self.escaped = false,
+            // Literal strings, the ones in single quotes, have no escapes.
+            Some('"') if c == '\\' => self.escaped = true,
+            Some(q) if q == c => self.quote = None,
+            Some(_) => {}
+        }
+
+        true
+    }
+}
+
+/// Returns the index of the first `needle` outside of strings.
+fn find_unquoted(s: &str, needle: char) -> Option<usize> {
+    let mut quotes = Quotes::default();
+    s.char_indices()
+        .find(|&(_, c)| !quotes.is_quoted(c) && c == needle)
+        .map(|(idx, _)| idx)
+}
+
+/// Splits a `key = value` pair at the first `=` outside of a quoted key.
+pub(crate) fn split_key_value(s: &str) -> Option<(&str, &str)> {
+    let idx = find_unquoted(s, '=')?;
+    Some((&s[..idx], &s[idx + 1..]))
+}
+
+pub(crate) fn strip_comment(line: &str) -> &str {
+    match find_unquoted(line, '#') {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+pub(crate) fn parse_value(s: &str) -> Option<json::JsonValue> {
+    if let Some(s) = s.strip_prefix('[') {
+        let s = s.strip_suffix(']')?.trim();
+        let mut array = json::JsonValue::new_array();
+        if !s.is_empty() {
+            for item in split_array(s) {
+                let item = item.trim();
+                // Allow a trailing comma.
+                if item.is_empty() {
+                    continue;
+                }
+
+                array.push(parse_value(item)?).ok()?;
+            }
+        }
+
+        return Some(array);
+    }
+
+    if let Some(s) = s.strip_prefix('"') {
+        return parse_basic_string(s).map(Into::into);
+    }
+
+    if let Some(s) = s.strip_prefix('\'') {
+        let s = s.strip_suffix('\'')?;
+        if s.contains('\'') {
+            return None;
+        }
+
+        return Some(s.into());
+    }
+
+    match s {
+        "true" => Some(true.into()),
+        "false" => Some(false.into()),
+        _ => s.replace('_', "").parse::<u64>().ok().map(Into::into),
+    }
+}
+
+/// Parses a string in double quotes, without the opening quote.
+///
+/// Only `\"` and `\\` escapes are supported, since the options are paths and regexes.
+fn parse_basic_string(s: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().is_empty() => return Some(value),
+            '"' => return None,
+            '\\' => match chars.next()? {
+                c @ '"' | c @ '\\' => value.push(c),
+                _ => return None,
+            },
+            c => value.push(c),
+        }
+    }
+
+    None
+}
+
+fn split_array(s: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut rest = s;
+    while let Some(idx) = find_unquoted(rest, ',') {
+        items.push(&rest[..idx]);
+        rest = &rest[idx + 1..];
+    }
+    items.push(rest);
+
+    items
+}
+
+/// Merges `other` into `base`, replacing existing keys.
+pub fn merge(base: &mut json::JsonValue, other: &json::JsonValue) {
+    for (key, value) in other.entries() {
+        base[key] = value.clone();
+    }
+}
+
+/// Converts config options to command line arguments.
+///
+/// Keys are the long option names, like `split-std` or `message-format`.
+/// Single letter keys, like `n` or `Z`, are treated as short options.
+/// `cargo-args` is an array of arguments that would be passed to `cargo build` as is.
+pub fn to_args(config: &json::JsonValue) -> Result<Vec<OsString>, String> {
+    let mut args = Vec::new();
+    let mut cargo_args = Vec::new();
+
+    for (key, value) in config.entries() {
+        let key = key.replace('_', "-");
+
+        if key == "cargo-args" {
+            for arg in value.members() {
+                let arg = arg
+                    .as_str()
+                    .ok_or("'cargo-args' must be an array of strings")?;
+                cargo_args.push(OsString::from(arg));
+            }
+
+            continue;
+        }
+
+        let flag = if key.len() == 1 {
+            format!("-{}", key)
+        } else {
+            format!("--{}", key)
+        };
+
+        if value.is_array() {
+            for item in value.members() {
+                args.push(OsString::from(&flag));
+                args.push(OsString::from(value_to_string(&key, item)?));
+            }
+        } else if let Some(b) = value.as_bool() {
+            if b {
+                args.push(OsString::from(flag));
+            }
+        } else {
+            args.push(OsString::from(flag));
+            args.push(OsString::from(value_to_string(&key, value)?));
+        }
+    }
+
+    if !cargo_args.is_empty() {
+        args.push(OsString::from("--"));
+        args.extend(cargo_args);
+    }
+
+    Ok(args)
+}
+
+fn value_to_string(key: &str, value: &json::JsonValue) -> Result<String, String> {
+    if let Some(s) = value.as_str() {
+        Ok(s.to_string())
+    } else if value.is_number() {
+        Ok(value.dump())
+    } else {
+        Err(format!("'{}' has an invalid value", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(config: &json::JsonValue) -> Vec<String> {
+        to_args(config)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn flat_table() {
+        let config = parse_toml(
+            "# defaults\n\
+             release = true\n\
+             n = 1_000\n\
+             filter = 'regex' # a comment\n\
+             \"split-std\" = true\n\
+             'message-format' = \"a=b#c\"\n\
+             cargo-args = [\"--offline\", \"--features=a,b\",]\n",
+        )
+        .unwrap();
+
+        assert_eq!(config["release"], true);
+        assert_eq!(config["n"], 1000);
+        assert_eq!(config["filter"], "regex");
+        assert_eq!(config["split-std"], true);
+        assert_eq!(config["message-format"], "a=b#c");
+        assert_eq!(config["cargo-args"][1], "--features=a,b");
+    }
+
+    #[test]
+    fn escapes() {
+        let config = parse_toml(
+            "filter = \"a\\\"#b\" # a comment\n\
+             sysroot = \"C:\\\\\\\"x\\\\\"\n\
+             cargo-args = [\"a\\\",b\", 'c\\d']\n",
+        )
+        .unwrap();
+
+        assert_eq!(config["filter"], "a\"#b");
+        assert_eq!(config["sysroot"], "C:\\\"x\\");
+        assert_eq!(config["cargo-args"].len(), 2);
+        assert_eq!(config["cargo-args"][0], "a\",b");
+        assert_eq!(config["cargo-args"][1], "c\\d");
+
+        assert!(parse_toml("filter = \"a\\nb\"\n").is_err());
+        assert!(parse_toml("filter = \"\\u00e9\"\n").is_err());
+        assert!(parse_toml("filter = \"a\"b\"\n").is_err());
+        assert!(parse_toml("filter = \"a\\\"\n").is_err());
+    }
+
+    #[test]
+    fn tables_are_rejected() {
+        assert!(parse_toml("crates = true\n[target.x86_64-unknown-linux-gnu]\n").is_err());
+        assert!(parse_toml("crates\n").is_err());
+        assert!(parse_toml("n = 1.5\n").is_err());
+    }
+
+    #[test]
+    fn command_line() {
+        let config = parse_toml(
+            "crates = true\n\
+             wide = false\n\
+             n = 50\n\
+             Z = [\"build-std\"]\n\
+             split_std = true\n\
+             cargo-args = [\"--offline\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            args(&config),
+            [
+                "--crates",
+                "-n",
+                "50",
+                "-Z",
+                "build-std",
+                "--split-std",
+                "--",
+                "--offline"
+            ]
+        );
+    }
+}
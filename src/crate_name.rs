@@ -1,12 +1,12 @@
-use crate::{Args, CrateData};
+use crate::{CrateData, Options};
 use binfarce::demangle::{self, SymbolName};
 
 pub const UNKNOWN: &str = "[Unknown]";
 
-pub fn from_sym(d: &CrateData, args: &Args, sym: &SymbolName) -> (String, bool) {
+pub fn from_sym(d: &CrateData, options: &Options, sym: &SymbolName) -> (String, bool) {
     let (mut name, is_exact) = from_sym_impl(d, sym);
 
-    if !args.split_std {
+    if !options.split_std {
         if d.std_crates.contains(&name) {
             name = "std".to_string();
         }
@@ -16,6 +16,11 @@ pub fn from_sym(d: &CrateData, args: &Args, sym: &SymbolName) -> (String, bool)
 }
 
 fn from_sym_impl(d: &CrateData, sym: &SymbolName) -> (String, bool) {
+    // Object files are exact, unlike symbol names.
+    if let Some(file) = d.data.symbol_objects.get(&sym.complete) {
+        return (crate::linker_map::crate_from_file(file), true);
+    }
+
     if let Some(name) = d.deps_symbols.get(&sym.complete) {
         return (name.to_string(), true);
     }
@@ -26,7 +31,11 @@ fn from_sym_impl(d: &CrateData, sym: &SymbolName) -> (String, bool) {
             Some(ref name) => (name.to_string(), true),
             None => parse_sym_v0(d, &sym.trimmed),
         },
-        demangle::Kind::Unknown => (UNKNOWN.to_string(), true),
+        // Set only for pseudo-symbols, like WebAssembly data segments.
+        demangle::Kind::Unknown => match sym.crate_name {
+            Some(ref name) => (name.to_string(), true),
+            None => (UNKNOWN.to_string(), true),
+        },
     }
 }
 
@@ -110,7 +119,11 @@ fn parse_crate_from_sym(sym: &str) -> String {
             crate_name.remove(0);
         }
 
-        crate_name = crate_name.split_whitespace().last().unwrap().to_owned();
+        crate_name = crate_name
+            .split_whitespace()
+            .last()
+            .unwrap_or_default()
+            .to_owned();
     }
 
     crate_name
@@ -0,0 +1,338 @@
+//! A minimal ELF reader for things `binfarce` doesn't provide,
+//! like program headers and non-function symbols.
+
+use std::convert::TryInto;
+use std::ops::Range;
+
+use binfarce::ByteOrder;
+
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_NOBITS: u32 = 8;
+
+pub const SHF_WRITE: u64 = 0x1;
+pub const SHF_ALLOC: u64 = 0x2;
+
+pub const PT_LOAD: u32 = 1;
+pub const PF_W: u32 = 0x2;
+
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+
+const EM_ARM: u16 = 40;
+
+pub struct Section {
+    pub index: usize,
+    pub name: String,
+    pub kind: u32,
+    pub flags: u64,
+    pub address: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub entry_size: u64,
+}
+
+impl Section {
+    pub fn range(&self) -> Option<Range<usize>> {
+        let start: usize = self.offset.try_into().ok()?;
+        let end = start.checked_add(self.size.try_into().ok()?)?;
+        Some(start..end)
+    }
+}
+
+pub struct Segment {
+    pub kind: u32,
+    pub flags: u32,
+    pub file_size: u64,
+    pub memory_size: u64,
+}
+
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub section_index: usize,
+}
+
+pub struct Elf<'a> {
+    data: &'a [u8],
+    is_64_bit: bool,
+    byte_order: ByteOrder,
+    machine: u16,
+    pub sections: Vec<Section>,
+    pub segments: Vec<Segment>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    byte_order: ByteOrder,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], offset: usize, byte_order: ByteOrder) -> Self {
+        Reader {
+            data,
+            offset,
+            byte_order,
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let end = self.offset.checked_add(N)?;
+        let bytes = self.data.get(self.offset..end)?.try_into().ok()?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let b = self.bytes()?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u16::from_le_bytes(b),
+            ByteOrder::BigEndian => u16::from_be_bytes(b),
+        })
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let b = self.bytes()?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u32::from_le_bytes(b),
+            ByteOrder::BigEndian => u32::from_be_bytes(b),
+        })
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let b = self.bytes()?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u64::from_le_bytes(b),
+            ByteOrder::BigEndian => u64::from_be_bytes(b),
+        })
+    }
+
+    /// Reads an address-sized value.
+    fn word(&mut self, is_64_bit: bool) -> Option<u64> {
+        if is_64_bit {
+            self.u64()
+        } else {
+            self.u32().map(u64::from)
+        }
+    }
+}
+
+fn null_string(data: &[u8], offset: usize) -> Option<&str> {
+    let data = data.get(offset..)?;
+    let len = data.iter().position(|c| *c == 0)?;
+    std::str::from_utf8(&data[..len]).ok()
+}
+
+pub fn parse(data: &[u8]) -> Option<Elf<'_>> {
+    if !data.starts_with(b"\x7FELF") {
+        return None;
+    }
+
+    let is_64_bit = match *data.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+
+    let byte_order = match *data.get(5)? {
+        1 => ByteOrder::LittleEndian,
+        2 => ByteOrder::BigEndian,
+        _ => return None,
+    };
+
+    let mut s = Reader::new(data, 16, byte_order);
+    s.u16()?; // type
+    let machine = s.u16()?;
+    s.u32()?; // version
+    s.word(is_64_bit)?; // entry
+    let phoff = s.word(is_64_bit)?;
+    let shoff = s.word(is_64_bit)?;
+    s.u32()?; // flags
+    s.u16()?; // ehsize
+    let phentsize = s.u16()?;
+    let phnum = s.u16()?;
+    let shentsize = s.u16()?;
+    let shnum = s.u16()?;
+    let shstrndx = s.u16()?;
+
+    let mut segments = Vec::with_capacity(phnum as usize);
+    for i in 0..phnum as usize {
+        let offset = (phoff as usize).checked_add(i.checked_mul(phentsize as usize)?)?;
+        let mut s = Reader::new(data, offset, byte_order);
+        // Note: the order of fields in 32 and 64 bit ELF is different.
+        let segment = if is_64_bit {
+            let kind = s.u32()?;
+            let flags = s.u32()?;
+            s.u64()?; // offset
+            s.u64()?; // virtual address
+            s.u64()?; // physical address
+            let file_size = s.u64()?;
+            let memory_size = s.u64()?;
+            Segment {
+                kind,
+                flags,
+                file_size,
+                memory_size,
+            }
+        } else {
+            let kind = s.u32()?;
+            s.u32()?; // offset
+            s.u32()?; // virtual address
+            s.u32()?; // physical address
+            let file_size = s.u32()?.into();
+            let memory_size = s.u32()?.into();
+            let flags = s.u32()?;
+            Segment {
+                kind,
+                flags,
+                file_size,
+                memory_size,
+            }
+        };
+        segments.push(segment);
+    }
+
+    let mut sections = Vec::with_capacity(shnum as usize);
+    let mut name_offsets = Vec::with_capacity(shnum as usize);
+    for index in 0..shnum as usize {
+        let offset = (shoff as usize).checked_add(index.checked_mul(shentsize as usize)?)?;
+        let mut s = Reader::new(data, offset, byte_order);
+        name_offsets.push(s.u32()? as usize);
+        let kind = s.u32()?;
+        let flags = s.word(is_64_bit)?;
+        let address = s.word(is_64_bit)?;
+        let offset = s.word(is_64_bit)?;
+        let size = s.word(is_64_bit)?;
+        let link = s.u32()?;
+        s.u32()?; // info
+        s.word(is_64_bit)?; // addralign
+        let entry_size = s.word(is_64_bit)?;
+        sections.push(Section {
+            index,
+            name: String::new(),
+            kind,
+            flags,
+            address,
+            offset,
+            size,
+            link,
+            entry_size,
+        });
+    }
+
+    if let Some(names) = sections.get(shstrndx as usize).and_then(|s| s.range()) {
+        if let Some(names) = data.get(names) {
+            for (section, offset) in sections.iter_mut().zip(name_offsets) {
+                section.name = null_string(names, offset).unwrap_or("").to_string();
+            }
+        }
+    }
+
+    Some(Elf {
+        data,
+        is_64_bit,
+        byte_order,
+        machine,
+        sections,
+        segments,
+    })
+}
+
+impl Elf<'_> {
+    /// Returns all named function and object symbols.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+
+        let symtab = match self.sections.iter().find(|s| s.kind == SHT_SYMTAB) {
+            Some(v) => v,
+            None => return symbols,
+        };
+
+        let strings = match self
+            .sections
+            .get(symtab.link as usize)
+            .and_then(|s| s.range())
+            .and_then(|r| self.data.get(r))
+        {
+            Some(v) => v,
+            None => return symbols,
+        };
+
+        let range = match symtab.range() {
+            Some(v) => v,
+            None => return symbols,
+        };
+
+        let entry_size = if symtab.entry_size != 0 {
+            symtab.entry_size as usize
+        } else if self.is_64_bit {
+            24
+        } else {
+            16
+        };
+
+        let mut offset = range.start;
+        while offset.saturating_add(entry_size) <= range.end {
+            let mut s = Reader::new(self.data, offset, self.byte_order);
+            offset += entry_size;
+
+            let sym = if self.is_64_bit {
+                (|| {
+                    let name = s.u32()?;
+                    let info = s.u8()?;
+                    s.u8()?; // other
+                    let shndx = s.u16()?;
+                    let value = s.u64()?;
+                    let size = s.u64()?;
+                    Some((name, info, shndx, value, size))
+                })()
+            } else {
+                (|| {
+                    let name = s.u32()?;
+                    let value = s.u32()?.into();
+                    let size = s.u32()?.into();
+                    let info = s.u8()?;
+                    s.u8()?; // other
+                    let shndx = s.u16()?;
+                    Some((name, info, shndx, value, size))
+                })()
+            };
+
+            let (name_offset, info, shndx, mut address, size) = match sym {
+                Some(v) => v,
+                None => break,
+            };
+
+            let kind = info & 0xf;
+            if kind != STT_FUNC && kind != STT_OBJECT {
+                continue;
+            }
+
+            if name_offset == 0 || shndx == 0 {
+                continue;
+            }
+
+            // Thumb functions have the lowest bit set.
+            if self.machine == EM_ARM && kind == STT_FUNC {
+                address &= !1;
+            }
+
+            if let Some(name) = null_string(strings, name_offset as usize) {
+                symbols.push(Symbol {
+                    name: name.to_string(),
+                    address,
+                    size,
+                    section_index: shndx as usize,
+                });
+            }
+        }
+
+        symbols
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fs, path};
+
+use binfarce::demangle::{SymbolData, SymbolName};
+
+use crate::{elf, linker_map, Data, Error};
+
+/// Sections that are attributed to symbols in the embedded mode.
+const SYMBOLS_SECTIONS: &[&str] = &[".vector_table", ".text", ".rodata"];
+
+/// Flash and RAM usage of an embedded binary.
+pub struct Memory {
+    pub flash_size: u64,
+    pub ram_size: u64,
+    pub sections: Vec<MemorySection>,
+    /// Sorted by the total size, the biggest ones first. Requires a linker map.
+    pub objects: Vec<ObjectUsage>,
+}
+
+pub struct MemorySection {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub in_flash: bool,
+    pub in_ram: bool,
+}
+
+pub struct ObjectUsage {
+    pub crate_name: String,
+    pub file: String,
+    pub flash_size: u64,
+    pub ram_size: u64,
+}
+
+pub(crate) fn collect_data(
+    path: &path::Path,
+    data: &[u8],
+    linker_map_path: Option<&path::Path>,
+) -> Result<Data, Error> {
+    let elf = elf::parse(data).ok_or_else(|| Error::UnsupportedFileFormat(path.to_owned()))?;
+
+    let mut sections = Vec::new();
+    for section in &elf.sections {
+        if section.flags & elf::SHF_ALLOC == 0 || section.size == 0 {
+            continue;
+        }
+
+        sections.push(MemorySection {
+            name: section.name.clone(),
+            address: section.address,
+            size: section.size,
+            in_flash: section.kind != elf::SHT_NOBITS,
+            in_ram: section.flags & elf::SHF_WRITE != 0,
+        });
+    }
+
+    // Flash stores the load images of all segments, including the initial values of `.data`,
+    // while RAM is occupied by writable segments only.
+    let loads = elf.segments.iter().filter(|s| s.kind == elf::PT_LOAD);
+    let (flash_size, ram_size) = if elf.segments.is_empty() {
+        (
+            sections.iter().filter(|s| s.in_flash).map(|s| s.size).sum(),
+            sections.iter().filter(|s| s.in_ram).map(|s| s.size).sum(),
+        )
+    } else {
+        (
+            loads.clone().map(|s| s.file_size).sum(),
+            loads
+                .filter(|s| s.flags & elf::PF_W != 0)
+                .map(|s| s.memory_size)
+                .sum(),
+        )
+    };
+
+    let objects = match linker_map_path {
+        Some(map_path) => collect_objects(map_path, &sections)?,
+        None => Vec::new(),
+    };
+
+    let mut all_symbols = elf.symbols();
+    all_symbols.sort_by_key(|s| s.address);
+
+    let mut text_size = 0;
+    let mut section_names = Vec::new();
+    let mut symbols = Vec::new();
+    for section in &elf.sections {
+        if !SYMBOLS_SECTIONS.contains(&section.name.as_str()) {
+            continue;
+        }
+
+        text_size += section.size;
+        section_names.push(section.name.as_str());
+
+        let mut section_symbols: Vec<&elf::Symbol> = all_symbols
+            .iter()
+            .filter(|s| s.section_index == section.index)
+            .collect();
+        section_symbols.dedup_by_key(|s| s.address);
+
+        // Merged or hand-written symbols often have no size,
+        // so assume that they span until the next symbol.
+        let end = section.address + section.size;
+        for (i, sym) in section_symbols.iter().enumerate() {
+            let size = if sym.size != 0 {
+                sym.size
+            } else {
+                let next = section_symbols.get(i + 1).map(|s| s.address).unwrap_or(end);
+                next.saturating_sub(sym.address)
+            };
+
+            if size != 0 {
+                symbols.push(SymbolData {
+                    name: SymbolName::demangle(&sym.name),
+                    address: sym.address,
+                    size,
+                });
+            }
+        }
+    }
+
+    Ok(Data {
+        symbols,
+        file_size: 0,
+        text_size,
+        section_name: Some(section_names.join("+")),
+        sections: Vec::new(),
+        memory: Some(Memory {
+            flash_size,
+            ram_size,
+            sections,
+            objects,
+        }),
+        symbol_objects: std::collections::HashMap::new(),
+        source_files: Vec::new(),
+        warnings: Vec::new(),
+        arch: None,
+    })
+}
+
+fn collect_objects(
+    map_path: &path::Path,
+    sections: &[MemorySection],
+) -> Result<Vec<ObjectUsage>, Error> {
+    let text =
+        fs::read_to_string(map_path).map_err(|e| Error::OpenFailed(map_path.to_owned(), e))?;
+
+    let mut objects: Vec<ObjectUsage> = Vec::new();
+    for input in linker_map::parse(&text) {
+        let output = match sections.iter().find(|s| s.name == input.output_section) {
+            Some(v) => v,
+            None => continue, // Not loaded, like debug info.
+        };
+
+        let idx = match objects.iter().position(|o| o.file == input.file) {
+            Some(idx) => idx,
+            None => {
+                objects.push(ObjectUsage {
+                    crate_name: linker_map::crate_from_file(&input.file),
+                    file: input.file.clone(),
+                    flash_size: 0,
+                    ram_size: 0,
+                });
+                objects.len() - 1
+            }
+        };
+
+        if output.in_flash {
+            objects[idx].flash_size += input.size;
+        }
+
+        if output.in_ram {
+            objects[idx].ram_size += input.size;
+        }
+    }
+
+    objects.sort_by(|a, b| {
+        (b.flash_size + b.ram_size)
+            .cmp(&(a.flash_size + a.ram_size))
+            .then(a.file.cmp(&b.file))
+    });
+
+    Ok(objects)
+}
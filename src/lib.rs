@@ -0,0 +1,2367 @@
+//! Find out what takes most of the space in your executable.
+//!
+//! This is the library behind the `cargo bloat` command.
+//! The analysis is split into separate steps:
+//!
+//! - [`build`] runs `cargo build` and analyzes produced binaries.
+//! - [`analyze_binary`] parses an already built binary into symbols,
+//!   and [`CrateData::new`] loads rlibs required to attribute them.
+//! - [`crate_of`] attributes a symbol to a crate.
+//! - [`crate_sizes`], [`filter_crates`] and [`filter_methods`] aggregate symbols.
+//! - [`diff_sizes`] compares two aggregations.
+//! - [`objects`], [`sources`], [`panic_cost`] and [`embedded`] provide other reports.
+//!
+//! All steps are configured by [`Options`]. Nothing is printed,
+//! and problems that didn't stop the analysis are collected in [`Data::warnings`].
+
+#![allow(clippy::collapsible_if)]
+#![allow(clippy::collapsible_else_if)]
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, IsTerminal};
+use std::process::{self, Command, Stdio};
+use std::sync::Arc;
+use std::{fmt, fs, path, str};
+
+use multimap::MultiMap;
+
+use binfarce::ar;
+pub use binfarce::demangle::{SymbolData, SymbolName};
+use binfarce::elf32;
+use binfarce::elf64;
+use binfarce::ByteOrder;
+use binfarce::Format;
+
+pub mod config;
+mod crate_name;
+mod elf;
+pub mod embedded;
+mod linker_map;
+mod macho;
+pub mod objects;
+pub mod panic_cost;
+mod parallel;
+mod pe;
+mod rustflags;
+pub mod sources;
+mod universal;
+mod wasm;
+
+pub use crate::sources::SourceFile;
+
+/// A crate name of symbols that cannot be attributed to any crate.
+pub const UNKNOWN_CRATE: &str = crate_name::UNKNOWN;
+
+/// Symbols and sizes of a single binary.
+pub struct Data {
+    pub symbols: Vec<SymbolData>,
+    pub file_size: u64,
+    pub text_size: u64,
+    pub section_name: Option<String>,
+    pub sections: Vec<(String, u64)>, // name, size
+    pub memory: Option<embedded::Memory>,
+    pub symbol_objects: HashMap<String, String>, // object files from a linker map or PDB
+    pub source_files: Vec<SourceFile>,           // from a PDB
+    /// Problems that didn't prevent the analysis, like a mismatched dSYM.
+    pub warnings: Vec<String>,
+    /// An architecture of a universal binary slice, like `arm64`.
+    pub arch: Option<String>,
+}
+
+/// A binary along with the information required to attribute its symbols to crates.
+///
+/// Crate lists and rlib symbols are shared by all binaries of a single build.
+pub struct CrateData {
+    pub exe_path: Option<String>,
+    pub data: Data,
+    pub std_crates: Arc<Vec<String>>,
+    pub dep_crates: Arc<Vec<String>>,
+    pub(crate) deps_symbols: Arc<MultiMap<String, String>>, // symbol, crate
+}
+
+impl CrateData {
+    /// Prepares a binary returned by [`analyze_binary`] for crate attribution.
+    ///
+    /// Crate names are taken from rlib file names, like `libserde-<hash>.rlib`.
+    /// std rlibs are usually in `<sysroot>/lib/rustlib/<target>/lib`
+    /// and dependency ones in `target/<profile>/deps`.
+    pub fn new(
+        exe_path: Option<String>,
+        data: Data,
+        std_rlibs: &[path::PathBuf],
+        dep_rlibs: &[path::PathBuf],
+    ) -> Result<Self, Error> {
+        let named = |paths: &[path::PathBuf]| -> Vec<(String, path::PathBuf)> {
+            paths
+                .iter()
+                .filter_map(|p| rlib_crate_name(p).map(|name| (name, p.clone())))
+                .collect()
+        };
+        let std_rlibs = named(std_rlibs);
+        let dep_rlibs = named(dep_rlibs);
+
+        let mut dep_crates: Vec<String> = dep_rlibs.iter().map(|v| v.0.clone()).collect();
+        dep_crates.sort();
+        dep_crates.dedup();
+
+        let mut std_crates: Vec<String> = std_rlibs.iter().map(|v| v.0.clone()).collect();
+        std_crates.sort();
+        std_crates.dedup();
+        std_crates.retain(|c| !dep_crates.contains(c));
+
+        let mut rlibs = dep_rlibs;
+        rlibs.extend(std_rlibs);
+
+        Ok(CrateData {
+            exe_path,
+            data,
+            std_crates: Arc::new(std_crates),
+            dep_crates: Arc::new(dep_crates),
+            deps_symbols: Arc::new(collect_deps_symbols(rlibs)?),
+        })
+    }
+}
+
+/// Returns rlibs in a directory, like `target/release/deps`.
+pub fn find_rlibs(dir: &path::Path) -> Vec<path::PathBuf> {
+    collect_rlib_paths(dir).into_iter().map(|v| v.1).collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ArtifactKind {
+    Binary,
+    Library,
+    DynLib,
+}
+
+#[derive(Debug)]
+struct Artifact {
+    kind: ArtifactKind,
+    name: String, // TODO: Rc?
+    path: path::PathBuf,
+    unit: String, // see `unit_key`
+    is_std: bool, // built by `-Z build-std`
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug)]
+pub enum Error {
+    StdDirNotFound(path::PathBuf),
+    RustcFailed(io::Error),
+    RustcError(String),
+    CargoError(String),
+    CargoFailed(io::Error),
+    CargoBuildFailed(process::ExitStatus, String), // status, stderr
+    GitError(String),
+    GitFailed(io::Error),
+    TuiFailed(io::Error),
+    UnsupportedCrateType,
+    OpenFailed(path::PathBuf, io::Error),
+    NonUtf8Path(path::PathBuf),
+    InvalidCargoOutput(String, String), // line, reason
+    InvalidArgs(String),
+    InvalidConfig(String),
+    InvalidMatrix(String),
+    NoArtifacts,
+    UnsupportedFileFormat(path::PathBuf),
+    ArchNotFound(String, Vec<String>),
+    ParsingError(binfarce::ParseError),
+    PdbError(pdb::Error),
+    PdbNotFound(path::PathBuf),
+    PdbMismatch(path::PathBuf),
+    NoHistory(path::PathBuf),
+}
+
+impl From<binfarce::ParseError> for Error {
+    fn from(e: binfarce::ParseError) -> Self {
+        Error::ParsingError(e)
+    }
+}
+
+impl From<binfarce::UnexpectedEof> for Error {
+    fn from(_: binfarce::UnexpectedEof) -> Self {
+        Error::ParsingError(binfarce::ParseError::UnexpectedEof)
+    }
+}
+
+impl From<pdb::Error> for Error {
+    fn from(e: pdb::Error) -> Self {
+        Error::PdbError(e)
+    }
+}
+
+impl Error {
+    /// Returns a stable error identifier, which is used in the JSON output.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Error::StdDirNotFound(..) => "std-dir-not-found",
+            Error::RustcFailed(..) => "rustc-failed",
+            Error::RustcError(..) => "rustc-error",
+            Error::CargoError(..) => "cargo-error",
+            Error::CargoFailed(..) => "cargo-failed",
+            Error::CargoBuildFailed(..) => "cargo-build-failed",
+            Error::GitError(..) => "git-error",
+            Error::GitFailed(..) => "git-failed",
+            Error::TuiFailed(..) => "tui-failed",
+            Error::UnsupportedCrateType => "unsupported-crate-type",
+            Error::OpenFailed(..) => "open-failed",
+            Error::NonUtf8Path(..) => "non-utf8-path",
+            Error::InvalidCargoOutput(..) => "invalid-cargo-output",
+            Error::InvalidArgs(..) => "invalid-args",
+            Error::InvalidConfig(..) => "invalid-config",
+            Error::InvalidMatrix(..) => "invalid-matrix",
+            Error::NoArtifacts => "no-artifacts",
+            Error::UnsupportedFileFormat(..) => "unsupported-file-format",
+            Error::ArchNotFound(..) => "arch-not-found",
+            Error::ParsingError(..) => "parsing-error",
+            Error::PdbError(..) => "pdb-error",
+            Error::PdbNotFound(..) => "pdb-not-found",
+            Error::PdbMismatch(..) => "pdb-mismatch",
+            Error::NoHistory(..) => "no-history",
+        }
+    }
+
+    /// Returns a path the error is related to.
+    pub fn path(&self) -> Option<&path::Path> {
+        match *self {
+            Error::StdDirNotFound(ref path)
+            | Error::OpenFailed(ref path, _)
+            | Error::NonUtf8Path(ref path)
+            | Error::UnsupportedFileFormat(ref path)
+            | Error::PdbNotFound(ref path)
+            | Error::PdbMismatch(ref path)
+            | Error::NoHistory(ref path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the error as a JSON object, like `{"error": {"kind": ..., "message": ...}}`.
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut error = json::JsonValue::new_object();
+        error["kind"] = self.kind().into();
+        error["message"] = self.to_string().into();
+
+        if let Some(path) = self.path() {
+            error["path"] = path.to_string_lossy().into_owned().into();
+        }
+
+        if let Error::CargoBuildFailed(ref status, _) = *self {
+            error["exit-code"] = status.code().into();
+        }
+
+        let mut causes = json::JsonValue::new_array();
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            causes.push(e.to_string()).unwrap();
+            source = e.source();
+        }
+        error["causes"] = causes;
+
+        let mut root = json::JsonValue::new_object();
+        root["error"] = error;
+        root
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::StdDirNotFound(ref path) => {
+                write!(
+                    f,
+                    "failed to find a dir with std libraries. Expected location: {}. \
+                     Use --sysroot or --std-rlibs to set it",
+                    path.display()
+                )
+            }
+            Error::RustcFailed(ref e) => {
+                write!(
+                    f,
+                    "failed to execute 'rustc' cause '{}'. It should be in the PATH",
+                    e
+                )
+            }
+            Error::RustcError(ref msg) => {
+                write!(f, "'rustc' failed: {}", msg)
+            }
+            Error::CargoError(ref msg) => {
+                write!(f, "{}", msg)
+            }
+            Error::CargoFailed(ref e) => {
+                write!(
+                    f,
+                    "failed to execute 'cargo' cause '{}'. It should be in the PATH",
+                    e
+                )
+            }
+            Error::CargoBuildFailed(ref status, ref stderr) => {
+                write!(f, "'cargo build' failed with {}", status)?;
+                if !stderr.is_empty() {
+                    write!(f, ":\n{}", stderr)?;
+                }
+
+                Ok(())
+            }
+            Error::GitError(ref msg) => {
+                write!(f, "git failed: {}", msg)
+            }
+            Error::GitFailed(ref e) => {
+                write!(
+                    f,
+                    "failed to execute 'git' cause '{}'. It should be in the PATH",
+                    e
+                )
+            }
+            Error::TuiFailed(ref e) => {
+                write!(f, "failed to use the terminal cause '{}'", e)
+            }
+            Error::UnsupportedCrateType => {
+                write!(
+                    f,
+                    "only 'bin', 'dylib' and 'cdylib' crate types are supported"
+                )
+            }
+            Error::OpenFailed(ref path, ref e) => {
+                write!(
+                    f,
+                    "failed to open a file '{}' cause '{}'",
+                    path.display(),
+                    e
+                )
+            }
+            Error::NonUtf8Path(ref path) => {
+                write!(f, "'{}' is not a valid UTF-8 path", path.display())
+            }
+            Error::InvalidCargoOutput(ref line, ref reason) => {
+                // Lines with build artifacts can be very long.
+                let line = match line.char_indices().nth(100) {
+                    Some((idx, _)) => format!("{}...", &line[..idx]),
+                    None => line.clone(),
+                };
+
+                write!(
+                    f,
+                    "failed to parse 'cargo' output '{}' cause '{}'",
+                    line, reason
+                )
+            }
+            Error::InvalidArgs(ref msg) => {
+                write!(f, "{}", msg)
+            }
+            Error::InvalidConfig(ref msg) => {
+                write!(f, "invalid config: {}", msg)
+            }
+            Error::InvalidMatrix(ref spec) => {
+                write!(
+                    f,
+                    "invalid matrix value '{}'. Expected 'KEY=VALUE,...'",
+                    spec
+                )
+            }
+            Error::NoArtifacts => {
+                write!(f, "'cargo' does not produce any build artifacts")
+            }
+            Error::UnsupportedFileFormat(ref path) => {
+                write!(f, "'{}' has an unsupported file format", path.display())
+            }
+            Error::ArchNotFound(ref arch, ref available) => {
+                write!(
+                    f,
+                    "the universal binary has no '{}' slice. Available: {}",
+                    arch,
+                    available.join(", ")
+                )
+            }
+            Error::ParsingError(ref e) => {
+                write!(f, "parsing failed cause '{}'", e)
+            }
+            Error::PdbError(ref e) => {
+                write!(f, "error parsing pdb file cause '{}'", e)
+            }
+            Error::PdbNotFound(ref path) => {
+                write!(
+                    f,
+                    "failed to find a pdb file. Expected location: {}. Use --pdb to set it",
+                    path.display()
+                )
+            }
+            Error::PdbMismatch(ref path) => {
+                write!(
+                    f,
+                    "'{}' doesn't match the executable. Rebuild the project or use --pdb",
+                    path.display()
+                )
+            }
+            Error::NoHistory(ref path) => {
+                write!(f, "'{}' has no records. Use --record first", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::RustcFailed(ref e)
+            | Error::CargoFailed(ref e)
+            | Error::GitFailed(ref e)
+            | Error::TuiFailed(ref e)
+            | Error::OpenFailed(_, ref e) => Some(e),
+            Error::ParsingError(ref e) => Some(e),
+            Error::PdbError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Build and analysis options.
+///
+/// Most of them match `cargo bloat` flags with the same name.
+#[derive(Clone, Default)]
+pub struct Options {
+    pub lib: bool,
+    pub bin: Option<String>,
+    pub example: Option<String>,
+    pub test: Option<String>,
+    pub bench: Option<String>,
+    pub bins: bool,
+    pub examples: bool,
+    pub all_targets: bool,
+    pub package: Option<String>,
+    pub release: bool,
+    pub jobs: Option<u32>,
+    pub features: Option<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub profile: Option<String>,
+    /// Profile settings to override, like `("opt-level", "s")`.
+    pub profile_overrides: Vec<(String, String)>,
+    /// Cargo config values or files, in the order of precedence.
+    pub config: Vec<String>,
+    pub target: Option<String>,
+    pub target_dir: Option<String>,
+    pub frozen: bool,
+    pub locked: bool,
+    pub unstable: Vec<String>,
+    pub manifest_path: Option<String>,
+    pub verbose: bool,
+    /// Arguments passed to `cargo build` as is.
+    pub cargo_args: Vec<String>,
+    /// Forward `cargo build` output to stderr.
+    pub cargo_output: bool,
+    pub filter: Option<String>,
+    pub split_std: bool,
+    pub full_fn: bool,
+    /// Number of items to return, 0 to return all.
+    pub n: usize,
+    pub sysroot: Option<String>,
+    pub std_rlibs: Option<String>,
+    pub toolchain: Option<String>,
+    pub symbols_section: Option<String>,
+    pub arch: Option<String>,
+    pub pdb: Option<String>,
+    pub embedded: bool,
+    pub linker_map: Option<String>,
+    pub emit_linker_map: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options {
+            n: 20,
+            ..Options::default()
+        }
+    }
+
+    pub fn get_profile(&self) -> &str {
+        if let Some(profile) = &self.profile {
+            profile
+        } else if self.release {
+            "release"
+        } else {
+            "dev"
+        }
+    }
+}
+
+fn stdlibs_dir(options: &Options, target_triple: &str) -> Result<path::PathBuf, Error> {
+    if let Some(ref dir) = options.std_rlibs {
+        let dir = path::PathBuf::from(dir);
+        if !dir.is_dir() {
+            return Err(Error::StdDirNotFound(dir));
+        }
+
+        return Ok(dir);
+    }
+
+    let sysroot = match options.sysroot {
+        Some(ref dir) => path::PathBuf::from(dir),
+        None => rustc_sysroot(options, target_triple)?,
+    };
+
+    // Custom targets are stored under the JSON file name without an extension.
+    let target_name = if target_triple.ends_with(".json") {
+        path::Path::new(target_triple)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or(target_triple)
+    } else {
+        target_triple
+    };
+
+    // From the `cargo` itself (this is a one long link):
+    // https://github.com/rust-lang/cargo/blob/065e3ef98d3edbce5c9e66d927d9ac9944cc6639
+    // /src/cargo/core/compiler/build_context/target_info.rs#L130..L133
+    let mut rustlib = sysroot;
+    rustlib.push("lib");
+    rustlib.push("rustlib");
+    rustlib.push(target_name);
+    rustlib.push("lib");
+
+    if !rustlib.exists() {
+        return Err(Error::StdDirNotFound(rustlib));
+    }
+
+    Ok(rustlib)
+}
+
+fn rustc_sysroot(options: &Options, target_triple: &str) -> Result<path::PathBuf, Error> {
+    // Support xargo and `--sysroot` in cargo config files by applying the rustflags
+    // the same way cargo does.
+    let target_cfg = rustc_target_cfg(options, target_triple)?;
+    let rustflags = rustflags::resolve(target_triple, &options.config, Some(&target_cfg));
+
+    let output = rustc_command(options)
+        .args(rustflags)
+        .arg("--print=sysroot")
+        .output()
+        .map_err(Error::RustcFailed)?;
+
+    let stdout = rustc_stdout(output)?;
+    Ok(path::PathBuf::from(stdout.trim()))
+}
+
+/// Returns a `cargo` command of the selected toolchain.
+///
+/// Cargo sets the `CARGO` variable for subcommands, so `cargo +nightly bloat`
+/// would not end up running a stable `cargo build`.
+pub fn cargo_command(options: &Options) -> Command {
+    match options.toolchain {
+        Some(ref toolchain) => {
+            let mut cmd = Command::new("cargo");
+            cmd.arg(format!("+{}", toolchain));
+            // Both point to the toolchain we were started with.
+            cmd.env_remove("CARGO");
+            cmd.env_remove("RUSTC");
+            cmd
+        }
+        None => Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into())),
+    }
+}
+
+/// Returns a `rustc` command of the selected toolchain.
+///
+/// Unless `RUSTC` is set, the one next to `CARGO` is preferred,
+/// since it belongs to the same toolchain.
+/// Returns the target's cfg values, like `unix` and `target_os="linux"`.
+fn rustc_target_cfg(options: &Options, target_triple: &str) -> Result<Vec<String>, Error> {
+    let output = rustc_command(options)
+        .args(["--print=cfg", "--target", target_triple])
+        .output()
+        .map_err(Error::RustcFailed)?;
+
+    let stdout = rustc_stdout(output)?;
+    Ok(stdout.lines().map(|line| line.trim().to_string()).collect())
+}
+
+fn rustc_command(options: &Options) -> Command {
+    if let Some(ref toolchain) = options.toolchain {
+        let mut cmd = Command::new("rustc");
+        cmd.arg(format!("+{}", toolchain));
+        return cmd;
+    }
+
+    if let Some(rustc) = std::env::var_os("RUSTC") {
+        return Command::new(rustc);
+    }
+
+    let sibling = std::env::var_os("CARGO").and_then(|cargo| {
+        let cargo = path::Path::new(&cargo);
+        let name = cargo.file_name()?.to_str()?.replacen("cargo", "rustc", 1);
+        let rustc = cargo.with_file_name(name);
+        if rustc.is_file() {
+            Some(rustc)
+        } else {
+            None
+        }
+    });
+
+    match sibling {
+        Some(rustc) => Command::new(rustc),
+        None => Command::new("rustc"),
+    }
+}
+
+fn get_default_target(options: &Options) -> Result<String, Error> {
+    let output = rustc_command(options)
+        .arg("-Vv")
+        .output()
+        .map_err(Error::RustcFailed)?;
+
+    let stdout = rustc_stdout(output)?;
+    for line in stdout.lines() {
+        if let Some(host) = line.strip_prefix("host:") {
+            return Ok(host.trim().to_owned());
+        }
+    }
+
+    Err(Error::RustcError(
+        "the host triple is missing in 'rustc -Vv' output".to_string(),
+    ))
+}
+
+fn rustc_stdout(output: process::Output) -> Result<String, Error> {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let msg = stderr.trim();
+        return Err(Error::RustcError(
+            msg.strip_prefix("error: ").unwrap_or(msg).to_string(),
+        ));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|_| Error::RustcError("the output is not a valid UTF-8".to_string()))
+}
+
+pub struct WorkspaceMetadata {
+    pub root: String,
+    /// Options from `[package.metadata.bloat]`, `[workspace.metadata.bloat]` and `bloat.toml`.
+    pub config: json::JsonValue,
+}
+
+pub fn workspace_metadata(options: &Options) -> Result<WorkspaceMetadata, Error> {
+    let mut cmd = cargo_command(options);
+    cmd.args(["metadata", "--format-version=1", "--no-deps"]);
+    if let Some(ref path) = options.manifest_path {
+        cmd.arg(format!("--manifest-path={}", path));
+    }
+
+    let output = cmd.output().map_err(Error::CargoFailed)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let msg = stderr.trim();
+        return Err(Error::CargoError(
+            msg.strip_prefix("error: ").unwrap_or(msg).to_string(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if let Some(line) = stdout.lines().next() {
+        let meta = json::parse(line)
+            .map_err(|e| Error::InvalidCargoOutput(line.to_string(), e.to_string()))?;
+        let root = meta["workspace_root"].as_str().ok_or_else(|| {
+            Error::InvalidCargoOutput(line.to_string(), "no workspace root".to_string())
+        })?;
+
+        // Options are merged in the following order, the latter ones take precedence:
+        // `[workspace.metadata.bloat]`, `[package.metadata.bloat]`, `bloat.toml`.
+        let mut config = json::JsonValue::new_object();
+        config::merge(&mut config, &meta["metadata"]["bloat"]);
+
+        let package = find_package(options, &meta);
+        if let Some(package) = package {
+            config::merge(&mut config, &package["metadata"]["bloat"]);
+        }
+
+        let mut config_dirs = Vec::new();
+        if let Some(path) = package.and_then(|p| p["manifest_path"].as_str()) {
+            if let Some(dir) = path::Path::new(path).parent() {
+                config_dirs.push(dir.to_path_buf());
+            }
+        }
+        config_dirs.push(path::PathBuf::from(root));
+
+        for dir in config_dirs {
+            let file =
+                config::load_file(&dir.join(config::FILE_NAME)).map_err(Error::InvalidConfig)?;
+            if let Some(file) = file {
+                config::merge(&mut config, &file);
+                break;
+            }
+        }
+
+        return Ok(WorkspaceMetadata {
+            root: root.to_string(),
+            config,
+        });
+    }
+
+    Err(Error::InvalidCargoOutput(
+        String::new(),
+        "no output".to_string(),
+    ))
+}
+
+/// Finds the package that would be built by `cargo build`.
+fn find_package<'a>(options: &Options, meta: &'a json::JsonValue) -> Option<&'a json::JsonValue> {
+    let packages = meta["packages"].members();
+
+    if let Some(ref spec) = options.package {
+        let name = spec.split(['@', ':']).next().unwrap_or(spec);
+        return packages.clone().find(|p| p["name"].as_str() == Some(name));
+    }
+
+    let dir = match options.manifest_path {
+        Some(ref path) => fs::canonicalize(path).ok()?.parent()?.to_path_buf(),
+        None => std::env::current_dir().ok()?,
+    };
+
+    // Like `cargo`, use the closest manifest.
+    for dir in dir.ancestors() {
+        for package in packages.clone() {
+            let manifest_dir = package["manifest_path"]
+                .as_str()
+                .and_then(|p| path::Path::new(p).parent());
+            if manifest_dir == Some(dir) {
+                return Some(package);
+            }
+        }
+    }
+
+    None
+}
+
+/// Builds the crate and analyzes every produced binary.
+pub fn build(options: &Options, workspace_root: &str) -> Result<Vec<CrateData>, Error> {
+    let default_target = get_default_target(options)?;
+    let target_triple = options.target.clone().unwrap_or(default_target);
+
+    let mut warnings = Vec::new();
+    let linker_map = if options.emit_linker_map {
+        linker_map_path(options, workspace_root, &target_triple, &mut warnings)?
+    } else {
+        options.linker_map.as_ref().map(path::PathBuf::from)
+    };
+
+    // Run a single `cargo build` and let cargo render compiler diagnostics to stderr,
+    // while the artifacts list is collected from the json output on stdout.
+    let cmd = &mut cargo_command(options);
+    cmd.args(get_cargo_args(options));
+    cmd.envs(get_cargo_envs(
+        options,
+        &target_triple,
+        linker_map.as_deref(),
+    )?);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let mut child = cmd.spawn().map_err(Error::CargoFailed)?;
+
+    // Both are always set, since they were piped.
+    let (stdout, stderr) = match (child.stdout.take(), child.stderr.take()) {
+        (Some(stdout), Some(stderr)) => (stdout, stderr),
+        _ => {
+            return Err(Error::CargoError(
+                "failed to capture 'cargo' output".to_string(),
+            ))
+        }
+    };
+    let forward = options.cargo_output;
+    let stderr_thread = std::thread::spawn(move || read_cargo_stderr(stderr, forward));
+
+    let mut artifacts = Vec::new();
+    let mut parse_error = None;
+    for line in BufReader::new(stdout).lines() {
+        let line = line.map_err(Error::CargoFailed)?;
+
+        // Keep reading even after an error, otherwise cargo may block on a full pipe.
+        if parse_error.is_some() {
+            continue;
+        }
+
+        match json::parse(&line) {
+            Ok(build) => collect_artifacts(&build, &mut artifacts),
+            Err(e) => parse_error = Some(Error::InvalidCargoOutput(line, e.to_string())),
+        }
+    }
+
+    let status = child.wait().map_err(Error::CargoFailed)?;
+    let stderr_lines = stderr_thread.join().unwrap_or_default();
+
+    if !status.success() {
+        let errors: Vec<&str> = stderr_lines
+            .iter()
+            .filter(|line| line.starts_with("error"))
+            .map(|line| line.strip_prefix("error: ").unwrap_or(line))
+            .collect();
+
+        if errors.is_empty() {
+            // Only the end of the output is relevant.
+            let tail = &stderr_lines[stderr_lines.len().saturating_sub(20)..];
+            return Err(Error::CargoBuildFailed(status, tail.join("\n")));
+        } else {
+            return Err(Error::CargoError(errors.join("\n")));
+        }
+    }
+
+    if let Some(e) = parse_error {
+        return Err(e);
+    }
+
+    if artifacts.is_empty() {
+        return Err(Error::NoArtifacts);
+    }
+
+    let build_std = options
+        .unstable
+        .iter()
+        .any(|unstable_arg| unstable_arg.starts_with("build-std"));
+
+    // Everything that can be reached only through std is a part of it.
+    if build_std {
+        match non_std_units(options, &target_triple, linker_map.as_deref()) {
+            Ok(units) => {
+                for artifact in &mut artifacts {
+                    artifact.is_std |= !units.contains(&artifact.unit);
+                }
+            }
+            Err(e) => warnings.push(format!(
+                "failed to resolve std dependencies cause '{}'. \
+                 They will be reported as separate crates.",
+                e
+            )),
+        }
+    }
+
+    let mut rlib_paths = Vec::new();
+
+    let mut dep_crates = Vec::new();
+    for artifact in &artifacts {
+        if !artifact.is_std {
+            dep_crates.push(artifact.name.clone());
+        }
+
+        if artifact.kind == ArtifactKind::Library {
+            rlib_paths.push((artifact.name.clone(), artifact.path.clone()));
+        }
+    }
+
+    dep_crates.sort();
+    dep_crates.dedup();
+
+    let std_crates: Vec<String> =
+        if build_std && options.sysroot.is_none() && options.std_rlibs.is_none() {
+            // std was built by cargo, so its rlibs are already in the artifacts list.
+            let mut std_crates: Vec<String> = artifacts
+                .iter()
+                .filter(|a| a.is_std)
+                .map(|a| a.name.clone())
+                .collect();
+            std_crates.sort();
+            std_crates.dedup();
+            std_crates
+        } else {
+            let target_dylib_path = stdlibs_dir(options, &target_triple)?;
+            let std_paths = collect_rlib_paths(&target_dylib_path);
+            let mut std_crates: Vec<String> = std_paths.iter().map(|v| v.0.clone()).collect();
+            rlib_paths.extend_from_slice(&std_paths);
+            std_crates.sort();
+
+            // Remove std crates that was explicitly added as dependencies.
+            //
+            // Like: getopts, bitflags, backtrace, log, etc.
+            for c in &dep_crates {
+                if let Some(idx) = std_crates.iter().position(|v| v == c) {
+                    std_crates.remove(idx);
+                }
+            }
+            std_crates
+        };
+
+    let deps_symbols = Arc::new(collect_deps_symbols(rlib_paths)?);
+    let std_crates = Arc::new(std_crates);
+    let dep_crates = Arc::new(dep_crates);
+
+    let prepare_path = |path: &path::Path| {
+        path.strip_prefix(workspace_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    // Every non-library artifact is our binary/dylib/cdylib.
+    // There could be multiple of them when building with `--bins`, `--all-targets`, etc.
+    let mut exe_artifacts: Vec<&Artifact> = Vec::new();
+    for artifact in &artifacts {
+        if artifact.kind != ArtifactKind::Library
+            && !exe_artifacts.iter().any(|a| a.path == artifact.path)
+        {
+            exe_artifacts.push(artifact);
+        }
+    }
+
+    if exe_artifacts.is_empty() {
+        return Err(Error::UnsupportedCrateType);
+    }
+
+    // Every link during the build, including build scripts, proc-macros and other binaries,
+    // overwrites the emitted map. So it's used only for the binary it actually describes.
+    let map_crates = match linker_map {
+        Some(ref map_path) if options.emit_linker_map => Some(linker_map_crates(map_path)?),
+        _ => None,
+    };
+
+    let mut list = Vec::with_capacity(exe_artifacts.len());
+    for artifact in exe_artifacts {
+        let exe_path = prepare_path(&artifact.path);
+        let linker_map = match map_crates {
+            Some(ref crates) if !crates.contains(&artifact.name) => {
+                warnings.push(format!(
+                    "the linker map doesn't describe '{}'. Using the symbol table instead.",
+                    exe_path
+                ));
+                None
+            }
+            _ => linker_map.as_deref(),
+        };
+
+        for (arch, data) in collect_self_data(&artifact.path, options, linker_map)? {
+            let exe_path = match arch {
+                Some(arch) => format!("{} [{}]", exe_path, arch),
+                None => exe_path.clone(),
+            };
+
+            list.push(CrateData {
+                exe_path: Some(exe_path),
+                data,
+                std_crates: std_crates.clone(),
+                dep_crates: dep_crates.clone(),
+                deps_symbols: deps_symbols.clone(),
+            });
+        }
+    }
+
+    // Warnings about the build as a whole are reported once.
+    if let Some(first) = list.first_mut() {
+        first.data.warnings.splice(0..0, warnings);
+    }
+
+    Ok(list)
+}
+
+fn collect_artifacts(build: &json::JsonValue, artifacts: &mut Vec<Artifact>) {
+    // Build scripts are binaries as well, but they are never a part of the output.
+    let is_build_script = build["target"]["kind"]
+        .members()
+        .any(|kind| kind == "custom-build");
+    if is_build_script {
+        return;
+    }
+
+    if let Some(target_name) = build["target"]["name"].as_str() {
+        if !build["filenames"].is_null() {
+            let filenames = build["filenames"].members();
+            let crate_types = build["target"]["crate_types"].members();
+            for (path, crate_type) in filenames.zip(crate_types) {
+                let kind = match crate_type.as_str() {
+                    Some("bin") => ArtifactKind::Binary,
+                    Some("lib") | Some("rlib") => ArtifactKind::Library,
+                    Some("dylib") | Some("cdylib") => ArtifactKind::DynLib,
+                    _ => continue, // Simply ignore.
+                };
+
+                let path = match path.as_str() {
+                    Some(path) => path,
+                    None => continue,
+                };
+
+                artifacts.push({
+                    Artifact {
+                        kind,
+                        name: target_name.replace('-', "_"),
+                        path: path::PathBuf::from(path),
+                        unit: unit_key(&build["package_id"], target_name, &build["features"]),
+                        is_std: is_std_manifest(build["manifest_path"].as_str().unwrap_or("")),
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Checks that a package comes from the `rust-src` component.
+fn is_std_manifest(path: &str) -> bool {
+    path.replace('\\', "/").contains("/lib/rustlib/src/rust/")
+}
+
+/// Identifies a compilation unit, since a crate can be built twice with `-Z build-std`:
+/// once as a dependency of std and once as a dependency of the crate itself.
+fn unit_key(package_id: &json::JsonValue, target_name: &str, features: &json::JsonValue) -> String {
+    let mut features: Vec<&str> = features.members().filter_map(|f| f.as_str()).collect();
+    features.sort_unstable();
+    format!(
+        "{} {} {}",
+        package_id.as_str().unwrap_or(""),
+        target_name,
+        features.join(",")
+    )
+}
+
+/// Returns units that are reachable from the build roots without going through std.
+///
+/// With `-Z build-std`, crates.io dependencies of std, like `hashbrown` or `libc`,
+/// are built along with the crate, so they cannot be detected by their manifest path.
+fn non_std_units(
+    options: &Options,
+    target_triple: &str,
+    linker_map: Option<&path::Path>,
+) -> Result<HashSet<String>, Error> {
+    let mut cmd = cargo_command(options);
+    cmd.args(get_cargo_args(options));
+    cmd.args(["-Z", "unstable-options", "--unit-graph"]);
+    cmd.envs(get_cargo_envs(options, target_triple, linker_map)?);
+
+    let output = cmd.output().map_err(Error::CargoFailed)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let msg = strip_ansi_codes(stderr.trim());
+        return Err(Error::CargoError(
+            msg.strip_prefix("error: ").unwrap_or(&msg).to_string(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let graph = json::parse(&stdout)
+        .map_err(|e| Error::InvalidCargoOutput(String::new(), e.to_string()))?;
+    let units = &graph["units"];
+
+    let mut keys = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut queue: Vec<usize> = graph["roots"]
+        .members()
+        .filter_map(|v| v.as_usize())
+        .collect();
+    while let Some(idx) = queue.pop() {
+        if !visited.insert(idx) {
+            continue;
+        }
+
+        let unit = &units[idx];
+        if is_std_manifest(unit["target"]["src_path"].as_str().unwrap_or("")) {
+            continue;
+        }
+
+        let target_name = unit["target"]["name"].as_str().unwrap_or("");
+        keys.insert(unit_key(&unit["pkg_id"], target_name, &unit["features"]));
+        queue.extend(
+            unit["dependencies"]
+                .members()
+                .filter_map(|d| d["index"].as_usize()),
+        );
+    }
+
+    Ok(keys)
+}
+
+/// Returns all `cargo` stderr lines without colors, optionally forwarding them as is.
+fn read_cargo_stderr(stderr: process::ChildStderr, forward: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in BufReader::new(stderr).lines() {
+        let line = match line {
+            Ok(v) => v,
+            Err(_) => break,
+        };
+
+        if forward {
+            eprintln!("{}", line);
+        }
+        lines.push(strip_ansi_codes(&line));
+    }
+
+    lines
+}
+
+fn strip_ansi_codes(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence, like `\x1b[1;31m`.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Returns the `target/bloat` directory, creating it if needed.
+pub fn bloat_dir(options: &Options, workspace_root: &str) -> Result<path::PathBuf, Error> {
+    let target_dir = options
+        .target_dir
+        .clone()
+        .or_else(|| std::env::var("CARGO_TARGET_DIR").ok())
+        .unwrap_or_else(|| format!("{}/target", workspace_root));
+
+    let dir = path::Path::new(&target_dir).join("bloat");
+    fs::create_dir_all(&dir).map_err(|e| Error::OpenFailed(dir.clone(), e))?;
+    Ok(dir)
+}
+
+/// Returns a path for a linker map that would be generated during the build.
+///
+/// The path must be stable between runs, since it's a part of `RUSTFLAGS`
+/// and changing it would trigger a full rebuild.
+fn linker_map_path(
+    options: &Options,
+    workspace_root: &str,
+    target_triple: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Option<path::PathBuf>, Error> {
+    if target_triple.contains("apple") {
+        warnings.push("linker maps are not supported for Apple targets.".to_string());
+        return Ok(None);
+    }
+
+    let dir = bloat_dir(options, workspace_root)?;
+    let name = format!("{}-{}.map", target_triple, options.get_profile());
+    Ok(Some(dir.join(name)))
+}
+
+/// Returns the names of crates whose object files were linked according to the map.
+fn linker_map_crates(map_path: &path::Path) -> Result<HashSet<String>, Error> {
+    let text =
+        fs::read_to_string(map_path).map_err(|e| Error::OpenFailed(map_path.to_owned(), e))?;
+    Ok(linker_map::parse(&text)
+        .iter()
+        .map(|input| linker_map::crate_from_file(&input.file))
+        .collect())
+}
+
+/// Returns rustc flags that make the linker produce a map file.
+///
+/// Existing flags, including the ones from cargo config files, are preserved,
+/// since `CARGO_ENCODED_RUSTFLAGS` overrides all of them. This includes
+/// `target.'cfg(...)'.rustflags` tables, which are matched against `rustc --print cfg`.
+fn linker_map_rustflags(
+    options: &Options,
+    target_triple: &str,
+    map_path: &path::Path,
+) -> Result<String, Error> {
+    let link_arg = if target_triple.contains("msvc") {
+        format!("/MAP:{}", map_path.display())
+    } else if target_triple.contains("-none") {
+        // Bare metal targets usually invoke `rust-lld` directly.
+        format!("-Map={}", map_path.display())
+    } else {
+        format!("-Wl,-Map={}", map_path.display())
+    };
+
+    let target_cfg = rustc_target_cfg(options, target_triple)?;
+    let mut flags = rustflags::resolve(target_triple, &options.config, Some(&target_cfg));
+    flags.push("-C".to_string());
+    flags.push(format!("link-arg={}", link_arg));
+    Ok(flags.join("\x1f"))
+}
+
+fn get_cargo_envs(
+    options: &Options,
+    target_triple: &str,
+    linker_map: Option<&path::Path>,
+) -> Result<Vec<(impl AsRef<OsStr>, impl AsRef<OsStr>)>, Error> {
+    let mut list = Vec::new();
+
+    let profile = options.get_profile().to_ascii_uppercase().replace('-', "_");
+
+    // No matter which profile we are building for, never strip the binary
+    // because we need the symbols.
+    list.push((
+        format!("CARGO_PROFILE_{}_STRIP", profile),
+        "false".to_string(),
+    ));
+
+    // When targeting MSVC, symbols data will be stored in PDB files.
+    // Because of that, the Release build would not have any useful information
+    // even if not stripped. Therefore, force the debug info for MSVC target.
+    if target_triple.contains("msvc") {
+        list.push((
+            format!("CARGO_PROFILE_{}_DEBUG", profile),
+            "true".to_string(),
+        ));
+    }
+
+    for (key, value) in &options.profile_overrides {
+        let key = key.to_ascii_uppercase().replace('-', "_");
+        list.push((format!("CARGO_PROFILE_{}_{}", profile, key), value.clone()));
+    }
+
+    if options.emit_linker_map {
+        if let Some(map_path) = linker_map {
+            let flags = linker_map_rustflags(options, target_triple, map_path)?;
+            list.push(("CARGO_ENCODED_RUSTFLAGS".to_string(), flags));
+        }
+    }
+
+    Ok(list)
+}
+
+#[allow(clippy::vec_init_then_push)]
+fn get_cargo_args(options: &Options) -> Vec<String> {
+    let mut list = Vec::new();
+    list.push("build".to_string());
+    list.push("--message-format=json-render-diagnostics".to_string());
+
+    // Cargo's stderr is piped through us, so we have to preserve colors manually.
+    if std::io::stderr().is_terminal() && std::env::var_os("CARGO_TERM_COLOR").is_none() {
+        list.push("--color=always".to_string());
+    }
+
+    if options.release {
+        list.push("--release".to_string());
+    }
+
+    if options.all_targets {
+        list.push("--all-targets".to_string());
+    } else {
+        if options.lib {
+            list.push("--lib".to_string());
+        }
+
+        if options.bins {
+            list.push("--bins".to_string());
+        } else if let Some(ref bin) = options.bin {
+            list.push(format!("--bin={}", bin));
+        }
+
+        if options.examples {
+            list.push("--examples".to_string());
+        } else if let Some(ref example) = options.example {
+            list.push(format!("--example={}", example));
+        }
+
+        if let Some(ref test) = options.test {
+            list.push(format!("--test={}", test));
+        }
+
+        if let Some(ref bench) = options.bench {
+            list.push(format!("--bench={}", bench));
+        }
+    }
+
+    if let Some(ref package) = options.package {
+        list.push(format!("--package={}", package));
+    }
+
+    if options.all_features {
+        list.push("--all-features".to_string());
+    } else {
+        if options.no_default_features {
+            list.push("--no-default-features".to_string());
+        }
+
+        if let Some(ref features) = options.features {
+            list.push(format!("--features={}", features));
+        }
+    }
+
+    if let Some(ref path) = options.manifest_path {
+        list.push(format!("--manifest-path={}", path))
+    }
+
+    if options.verbose {
+        list.push("-v".into());
+    }
+
+    if let Some(ref profile) = options.profile {
+        list.push(format!("--profile={}", profile));
+    }
+
+    for config in &options.config {
+        list.push(format!("--config={}", config));
+    }
+
+    if let Some(ref target) = options.target {
+        list.push(format!("--target={}", target));
+    }
+
+    if let Some(ref target_dir) = options.target_dir {
+        list.push(format!("--target-dir={}", target_dir));
+    }
+
+    if options.frozen {
+        list.push("--frozen".to_string());
+    }
+
+    if options.locked {
+        list.push("--locked".to_string());
+    }
+
+    for arg in &options.unstable {
+        list.push(format!("-Z={}", arg));
+    }
+
+    if let Some(jobs) = options.jobs {
+        list.push(format!("-j{}", jobs));
+    }
+
+    list.extend(options.cargo_args.iter().cloned());
+
+    list
+}
+
+fn collect_rlib_paths(deps_dir: &path::Path) -> Vec<(String, path::PathBuf)> {
+    let mut rlib_paths: Vec<(String, path::PathBuf)> = Vec::new();
+    if let Ok(entries) = fs::read_dir(deps_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(name) = rlib_crate_name(&path) {
+                rlib_paths.push((name, path));
+            }
+        }
+    }
+
+    rlib_paths.sort_by(|a, b| a.0.cmp(&b.0));
+
+    rlib_paths
+}
+
+/// Returns a crate name of an rlib, like `serde` for `libserde-0123456789abcdef.rlib`.
+fn rlib_crate_name(path: &path::Path) -> Option<String> {
+    if path.extension().and_then(|s| s.to_str()) != Some("rlib") {
+        return None;
+    }
+
+    let mut stem = path.file_stem()?.to_str()?.to_string();
+    if let Some(idx) = stem.bytes().position(|b| b == b'-') {
+        stem.drain(idx..);
+    }
+
+    Some(stem.strip_prefix("lib").unwrap_or(&stem).to_string())
+}
+
+fn map_file(path: &path::Path) -> Result<memmap2::Mmap, Error> {
+    let file = fs::File::open(path).map_err(|e| Error::OpenFailed(path.to_owned(), e))?;
+    let file =
+        unsafe { memmap2::Mmap::map(&file).map_err(|e| Error::OpenFailed(path.to_owned(), e))? };
+    Ok(file)
+}
+
+fn collect_deps_symbols(
+    libs: Vec<(String, path::PathBuf)>,
+) -> Result<MultiMap<String, String>, Error> {
+    // Parsing archives is the most expensive part, so do it in parallel
+    // and merge the results in the original order afterwards.
+    let parsed = parallel::map(&libs, |(_, path)| -> Result<Vec<String>, Error> {
+        let file = map_file(path)?;
+        Ok(ar::parse(&file)?)
+    });
+
+    let mut map = MultiMap::new();
+    for ((name, _), symbols) in libs.iter().zip(parsed) {
+        for sym in symbols? {
+            map.insert(sym, name.clone());
+        }
+    }
+
+    for (_, v) in map.iter_all_mut() {
+        v.dedup();
+    }
+
+    Ok(map)
+}
+
+/// Parses an already built binary.
+///
+/// Universal binaries produce an entry per architecture.
+pub fn analyze_binary(
+    path: &path::Path,
+    options: &Options,
+) -> Result<Vec<(Option<String>, Data)>, Error> {
+    collect_self_data(
+        path,
+        options,
+        options.linker_map.as_deref().map(path::Path::new),
+    )
+}
+
+fn collect_self_data(
+    path: &path::Path,
+    options: &Options,
+    linker_map: Option<&path::Path>,
+) -> Result<Vec<(Option<String>, Data)>, Error> {
+    let data = &map_file(path)?;
+
+    // Universal binaries are analyzed per architecture.
+    let slices = match universal::parse(data) {
+        Some(v) => v,
+        None => {
+            let mut d = collect_binary_data(path, data, options, linker_map)?;
+            if let Some(ref arch) = options.arch {
+                d.warnings.push(format!(
+                    "'{}' is not a universal binary, so the '{}' architecture selection is ignored.",
+                    path.display(),
+                    arch
+                ));
+            }
+
+            return Ok(vec![(None, d)]);
+        }
+    };
+
+    let available: Vec<String> = slices.iter().map(|s| s.arch.clone()).collect();
+    let mut list: Vec<(Option<String>, Data)> = Vec::new();
+    let mut warnings = Vec::new();
+    for slice in slices {
+        match options.arch {
+            Some(ref arch) if *arch != slice.arch => continue,
+            Some(_) => {}
+            None => {
+                if macho::parse(slice.data).is_none() {
+                    warnings.push(format!(
+                        "the '{}' slice is not supported. Skipped.",
+                        slice.arch
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        let mut d = collect_binary_data(path, slice.data, options, linker_map)?;
+        d.arch = Some(slice.arch.clone());
+        list.push((Some(slice.arch), d));
+    }
+
+    if list.is_empty() {
+        return match options.arch {
+            Some(ref arch) => Err(Error::ArchNotFound(arch.clone(), available)),
+            None => Err(Error::UnsupportedFileFormat(path.to_owned())),
+        };
+    }
+
+    list[0].1.warnings.splice(0..0, warnings);
+    Ok(list)
+}
+
+fn collect_binary_data(
+    path: &path::Path,
+    data: &[u8],
+    options: &Options,
+    linker_map: Option<&path::Path>,
+) -> Result<Data, Error> {
+    let section_name = options.symbols_section.as_deref().unwrap_or(".text");
+    let mut d = match binfarce::detect_format(data) {
+        Format::Unknown if wasm::is_wasm(data) => wasm::collect_data(path, data)?,
+        Format::Elf32 { .. } | Format::Elf64 { .. } if options.embedded => {
+            embedded::collect_data(path, data, linker_map)?
+        }
+        Format::Elf32 { byte_order: _ } => collect_elf_data(path, data, section_name)?,
+        Format::Elf64 { byte_order: _ } => collect_elf_data(path, data, section_name)?,
+        Format::Macho => collect_macho_data(path, data, options.symbols_section.as_deref())?,
+        Format::Unknown if macho::parse(data).is_some() => {
+            collect_macho_data(path, data, options.symbols_section.as_deref())?
+        }
+        Format::PE => {
+            let pdb_path = options.pdb.as_ref().map(path::Path::new);
+            collect_pe_data(path, data, section_name, pdb_path)?
+        }
+        Format::Unknown => return Err(Error::UnsupportedFileFormat(path.to_owned())),
+    };
+
+    // A linker map knows exactly which object file each function came from,
+    // so prefer it over the symbol table.
+    if let (Some(map_path), false) = (linker_map, options.embedded) {
+        let text =
+            fs::read_to_string(map_path).map_err(|e| Error::OpenFailed(map_path.to_owned(), e))?;
+        let sections = linker_map::parse(&text);
+        let (symbols, symbol_objects) = linker_map::collect_symbols(&sections, section_name);
+        if symbols.is_empty() {
+            d.warnings.push(format!(
+                "the linker map has no '{}' entries. Using the symbol table instead.",
+                section_name
+            ));
+        } else {
+            d.symbols = symbols;
+            d.symbol_objects = symbol_objects;
+        }
+    }
+
+    // Multiple symbols may point to the same address.
+    // Remove duplicates.
+    d.symbols.sort_by_key(|v| v.address);
+    d.symbols.dedup_by_key(|v| v.address);
+
+    d.file_size = data.len() as u64;
+    d.sections = collect_section_sizes(data);
+
+    Ok(d)
+}
+
+/// Sections that are interesting on their own, like unwinding tables.
+const ELF_SECTIONS: &[&str] = &[
+    ".text",
+    ".rodata",
+    ".data",
+    ".bss",
+    ".eh_frame",
+    ".eh_frame_hdr",
+    ".gcc_except_table",
+];
+
+const MACHO_SECTIONS: &[&str] = &[
+    "__TEXT,__text",
+    "__TEXT,__const",
+    "__TEXT,__cstring",
+    "__TEXT,__eh_frame",
+    "__TEXT,__unwind_info",
+    "__TEXT,__gcc_except_tab",
+    "__DATA,__data",
+    "__DATA,__bss",
+];
+
+fn collect_section_sizes(data: &[u8]) -> Vec<(String, u64)> {
+    fn size(range: Result<std::ops::Range<usize>, binfarce::ParseError>) -> u64 {
+        range.map(|r| r.len() as u64).unwrap_or(0)
+    }
+
+    let mut sections = Vec::new();
+    match binfarce::detect_format(data) {
+        Format::Elf32 { byte_order } => {
+            if let Ok(elf) = elf32::parse(data, byte_order) {
+                for name in ELF_SECTIONS {
+                    if let Ok(Some(section)) = elf.section_with_name(name) {
+                        sections.push((name.to_string(), size(section.range())));
+                    }
+                }
+            }
+        }
+        Format::Elf64 { byte_order } => {
+            if let Ok(elf) = elf64::parse(data, byte_order) {
+                for name in ELF_SECTIONS {
+                    if let Ok(Some(section)) = elf.section_with_name(name) {
+                        sections.push((name.to_string(), size(section.range())));
+                    }
+                }
+            }
+        }
+        Format::Macho => {
+            if let Some(file) = macho::parse(data) {
+                for name in MACHO_SECTIONS {
+                    if let Some(section) = file.find_section(name) {
+                        let size = section.range().map(|r| r.len() as u64).unwrap_or(0);
+                        sections.push((name.to_string(), size));
+                    }
+                }
+            }
+        }
+        Format::PE => {
+            if let Some(file) = pe::parse(data) {
+                for section in &file.sections {
+                    sections.push((section.name.clone(), section.size()));
+                }
+            }
+        }
+        Format::Unknown => {
+            if wasm::is_wasm(data) {
+                sections = wasm::section_sizes(data);
+            }
+        }
+    }
+
+    sections
+}
+
+fn collect_elf_data(path: &path::Path, data: &[u8], section_name: &str) -> Result<Data, Error> {
+    let is_64_bit = match data[4] {
+        1 => false,
+        2 => true,
+        _ => return Err(Error::UnsupportedFileFormat(path.to_owned())),
+    };
+
+    let byte_order = match data[5] {
+        1 => ByteOrder::LittleEndian,
+        2 => ByteOrder::BigEndian,
+        _ => return Err(Error::UnsupportedFileFormat(path.to_owned())),
+    };
+
+    let (symbols, text_size) = if is_64_bit {
+        elf64::parse(data, byte_order)?.symbols(section_name)?
+    } else {
+        elf32::parse(data, byte_order)?.symbols(section_name)?
+    };
+
+    let d = Data {
+        symbols,
+        file_size: 0,
+        text_size,
+        section_name: Some(section_name.to_owned()),
+        sections: Vec::new(),
+        memory: None,
+        symbol_objects: HashMap::new(),
+        source_files: Vec::new(),
+        warnings: Vec::new(),
+        arch: None,
+    };
+
+    Ok(d)
+}
+
+fn collect_macho_data(
+    path: &path::Path,
+    data: &[u8],
+    section_name: Option<&str>,
+) -> Result<Data, Error> {
+    let file = macho::parse(data).ok_or_else(|| Error::UnsupportedFileFormat(path.to_owned()))?;
+    let section = file
+        .find_section(section_name.unwrap_or(macho::DEFAULT_SECTION))
+        .ok_or(Error::ParsingError(
+            binfarce::ParseError::SymbolsSectionIsMissing,
+        ))?;
+
+    let mut symbols = file.symbols(section);
+    let mut warnings = Vec::new();
+
+    // A binary stripped after `dsymutil` has symbols only in its dSYM bundle.
+    if symbols.is_empty() {
+        symbols = collect_dsym_symbols(path, &file, section, &mut warnings);
+    }
+
+    let d = Data {
+        symbols,
+        file_size: 0,
+        text_size: section.size,
+        section_name: section_name.map(|_| section.full_name()),
+        sections: Vec::new(),
+        memory: None,
+        symbol_objects: HashMap::new(),
+        source_files: Vec::new(),
+        warnings,
+        arch: None,
+    };
+
+    Ok(d)
+}
+
+fn collect_dsym_symbols(
+    path: &path::Path,
+    binary: &macho::MachO,
+    section: &macho::Section,
+    warnings: &mut Vec<String>,
+) -> Vec<SymbolData> {
+    for dsym_path in macho::find_dsym_files(path) {
+        let data = match map_file(&dsym_path) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+
+        // A dSYM of a universal binary is universal as well.
+        let slices = match universal::parse(&data) {
+            Some(slices) => slices.into_iter().map(|s| s.data).collect(),
+            None => vec![&data[..]],
+        };
+
+        let mut is_matched = false;
+        for slice in slices {
+            let dsym = match macho::parse(slice) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            // Never use symbols from a stale dSYM.
+            if dsym.uuid.is_none() || dsym.uuid != binary.uuid {
+                continue;
+            }
+
+            is_matched = true;
+            if let Some(dsym_section) = dsym.find_section(&section.full_name()) {
+                return dsym.symbols(dsym_section);
+            }
+        }
+
+        if is_matched {
+            warnings.push(format!(
+                "'{}' has no '{}' section. Ignored.",
+                dsym_path.display(),
+                section.full_name()
+            ));
+        } else {
+            warnings.push(format!(
+                "'{}' doesn't match the binary. Ignored.",
+                dsym_path.display()
+            ));
+        }
+    }
+
+    Vec::new()
+}
+
+/// Finds a PDB file that matches the executable.
+///
+/// Candidates are checked in the following order: the path from the CodeView record,
+/// `--pdb`, a file next to the executable and symbol stores from `_NT_SYMBOL_PATH`.
+fn find_pdb(
+    exe_path: &path::Path,
+    file: &pe::Pe,
+    pdb_override: Option<&path::Path>,
+) -> Result<path::PathBuf, Error> {
+    // Cargo renames `my-app.exe` to `my_app.pdb`.
+    let legacy_path = {
+        let file_name = exe_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| Error::NonUtf8Path(exe_path.to_owned()))?;
+        exe_path
+            .with_file_name(file_name.replace('-', "_"))
+            .with_extension("pdb")
+    };
+
+    let codeview = match file.codeview() {
+        Some(v) => v,
+        None => {
+            // Nothing to check against, so trust the user.
+            let path = pdb_override
+                .map(path::Path::to_path_buf)
+                .unwrap_or(legacy_path);
+            return if path.is_file() {
+                Ok(path)
+            } else {
+                Err(Error::PdbNotFound(path))
+            };
+        }
+    };
+
+    let pdb_name = codeview
+        .path
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(&codeview.path)
+        .to_string();
+
+    let mut stores = Vec::new();
+    let mut candidates = vec![path::PathBuf::from(&codeview.path)];
+    if let Some(path) = pdb_override {
+        if path.is_dir() {
+            candidates.push(path.join(&pdb_name));
+            stores.push(path.to_path_buf());
+        } else {
+            candidates.push(path.to_path_buf());
+        }
+    }
+    candidates.push(exe_path.with_file_name(&pdb_name));
+    candidates.push(legacy_path);
+
+    stores.extend(symbol_store_dirs());
+    for store in stores {
+        candidates.push(
+            store
+                .join(&pdb_name)
+                .join(codeview.signature())
+                .join(&pdb_name),
+        );
+    }
+
+    // A broken candidate must not hide a valid one further in the list,
+    // so its error is reported only when nothing else was found.
+    let mut mismatched = None;
+    let mut first_error = None;
+    for path in candidates.iter().filter(|p| p.is_file()) {
+        match pdb_matches(path, &codeview) {
+            Ok(true) => return Ok(path.clone()),
+            Ok(false) => {
+                mismatched.get_or_insert_with(|| path.clone());
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (mismatched, first_error) {
+        (Some(path), _) => Err(Error::PdbMismatch(path)),
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(Error::PdbNotFound(candidates.remove(0))),
+    }
+}
+
+/// Checks that a PDB file was produced together with the executable.
+///
+/// Never use a stale PDB silently. The GUID changes on a full relink,
+/// while the age is bumped by incremental links.
+fn pdb_matches(path: &path::Path, codeview: &pe::CodeView) -> Result<bool, Error> {
+    let file = fs::File::open(path).map_err(|e| Error::OpenFailed(path.to_owned(), e))?;
+    let mut pdb = pdb::PDB::open(file)?;
+    let info = pdb.pdb_information()?;
+    let (d1, d2, d3, d4) = info.guid.as_fields();
+    if (d1, d2, d3, *d4) != codeview.guid {
+        return Ok(false);
+    }
+
+    // The DBI stream age is written by the linker and must be equal to the executable one.
+    // The PDB stream age can be bumped by other tools, so it's only a fallback for old files.
+    let is_same_age = match pdb.debug_information()?.age() {
+        Some(age) => age == codeview.age,
+        None => info.age >= codeview.age,
+    };
+
+    Ok(is_same_age)
+}
+
+/// Returns local directories from `_NT_SYMBOL_PATH`,
+/// like `srv*C:\symbols*https://msdl.microsoft.com/download/symbols`.
+fn symbol_store_dirs() -> Vec<path::PathBuf> {
+    let value = std::env::var("_NT_SYMBOL_PATH").unwrap_or_default();
+
+    let mut dirs = Vec::new();
+    for entry in value.split(';') {
+        for part in entry.split('*') {
+            let lower = part.to_ascii_lowercase();
+            if part.is_empty()
+                || ["srv", "cache", "symsrv"].contains(&lower.as_str())
+                || lower.ends_with(".dll")
+                || part.contains("://")
+            {
+                continue;
+            }
+
+            dirs.push(path::PathBuf::from(part));
+        }
+    }
+
+    dirs
+}
+
+fn collect_pdb_data(pdb_path: &path::Path, section: &pe::Section) -> Result<Data, Error> {
+    use pdb::FallibleIterator;
+
+    let file = fs::File::open(pdb_path).map_err(|e| Error::OpenFailed(pdb_path.to_owned(), e))?;
+    let mut pdb = pdb::PDB::open(file)?;
+
+    let dbi = pdb.debug_information()?;
+    let symbol_table = pdb.global_symbols()?;
+    let address_map = pdb.address_map()?;
+
+    let mut out_symbols = Vec::new();
+
+    // Collect the PublicSymbols.
+    let mut public_symbols = Vec::new();
+
+    let mut symbols = symbol_table.iter();
+    while let Ok(Some(symbol)) = symbols.next() {
+        if let Ok(pdb::SymbolData::Public(data)) = symbol.parse() {
+            public_symbols.push((data.offset, data.name.to_string().into_owned()));
+        }
+    }
+
+    let mut modules = dbi.modules()?;
+    while let Some(module) = modules.next()? {
+        let info = match pdb.module_info(&module)? {
+            Some(info) => info,
+            None => continue,
+        };
+        let mut symbols = info.symbols()?;
+        while let Some(symbol) = symbols.next()? {
+            if let Ok(pdb::SymbolData::Public(data)) = symbol.parse() {
+                public_symbols.push((data.offset, data.name.to_string().into_owned()));
+            }
+        }
+    }
+
+    let cmp_offsets = |a: &pdb::PdbInternalSectionOffset, b: &pdb::PdbInternalSectionOffset| {
+        a.section.cmp(&b.section).then(a.offset.cmp(&b.offset))
+    };
+    public_symbols.sort_unstable_by(|a, b| cmp_offsets(&a.0, &b.0));
+
+    // Now find the Procedure symbols in all modules
+    // and if possible the matching PublicSymbol record with the mangled name.
+    let mut handle_proc = |proc: pdb::ProcedureSymbol, object: Option<&str>| {
+        let mangled_symbol = public_symbols
+            .binary_search_by(|probe| {
+                let low = cmp_offsets(&probe.0, &proc.offset);
+                let high = cmp_offsets(&probe.0, &(proc.offset + proc.len));
+
+                use std::cmp::Ordering::*;
+                match (low, high) {
+                    // Less than the low bound -> less.
+                    (Less, _) => Less,
+                    // More than the high bound -> greater.
+                    (_, Greater) => Greater,
+                    _ => Equal,
+                }
+            })
+            .ok()
+            .map(|x| &public_symbols[x]);
+
+        let demangled_name = proc.name.to_string().into_owned();
+        out_symbols.push((
+            proc.offset.to_rva(&address_map),
+            proc.len as u64,
+            demangled_name,
+            mangled_symbol,
+            object.map(str::to_string),
+        ));
+    };
+
+    // `S_LDATA32` and `S_GDATA32` records. They don't have a size,
+    // which will be calculated using the next symbol later.
+    let mut data_symbols = Vec::new();
+    let mut handle_data = |data: pdb::DataSymbol, object: Option<&str>| {
+        let mangled_symbol = public_symbols
+            .binary_search_by(|probe| cmp_offsets(&probe.0, &data.offset))
+            .ok()
+            .map(|x| &public_symbols[x]);
+
+        data_symbols.push((
+            data.offset.to_rva(&address_map),
+            0,
+            data.name.to_string().into_owned(),
+            mangled_symbol,
+            object.map(str::to_string),
+        ));
+    };
+
+    let mut symbols = symbol_table.iter();
+    while let Ok(Some(symbol)) = symbols.next() {
+        match symbol.parse() {
+            Ok(pdb::SymbolData::Procedure(proc)) => handle_proc(proc, None),
+            Ok(pdb::SymbolData::Data(data)) => handle_data(data, None),
+            _ => {}
+        }
+    }
+
+    let range = section.rva_range();
+    let strings = pdb.string_table().ok();
+    let mut line_ranges = sources::LineRanges::default();
+
+    // Each module is an object file, which may be a library member,
+    // like `libserde-xxx.rlib(serde-xxx.serde.xxx-cgu.0.rcgu.o)`.
+    let mut modules = dbi.modules()?;
+    while let Some(module) = modules.next()? {
+        let info = match pdb.module_info(&module)? {
+            Some(info) => info,
+            None => continue,
+        };
+
+        let module_name = module.module_name();
+        let library_name = module.object_file_name();
+        let object = if library_name != module_name {
+            format!("{}({})", library_name, module_name)
+        } else {
+            module_name.into_owned()
+        };
+
+        let mut symbols = info.symbols()?;
+
+        while let Some(symbol) = symbols.next()? {
+            match symbol.parse() {
+                Ok(pdb::SymbolData::Procedure(proc)) => handle_proc(proc, Some(&object)),
+                Ok(pdb::SymbolData::Data(data)) => handle_data(data, Some(&object)),
+                _ => {}
+            }
+        }
+
+        // Line tables are optional and must not prevent symbols collection.
+        if let Some(ref strings) = strings {
+            let _ = collect_pdb_lines(&info, strings, &address_map, &range, &mut line_ranges);
+        }
+    }
+
+    // Keep only symbols from the requested section.
+    let in_section = |v: &(
+        Option<pdb::Rva>,
+        u64,
+        String,
+        Option<&(_, String)>,
+        Option<String>,
+    )| {
+        v.0.map(|rva| range.contains(&u64::from(rva.0)))
+            .unwrap_or(false)
+    };
+    out_symbols.retain(in_section);
+    data_symbols.retain(in_section);
+
+    data_symbols.sort_by_key(|v| v.0);
+    data_symbols.dedup_by_key(|v| v.0);
+    for i in 0..data_symbols.len() {
+        let address = data_symbols[i].0.map(|v| u64::from(v.0)).unwrap_or(0);
+        let next = data_symbols
+            .get(i + 1)
+            .and_then(|v| v.0)
+            .map(|v| u64::from(v.0))
+            .unwrap_or(range.end);
+        data_symbols[i].1 = next.saturating_sub(address);
+    }
+    out_symbols.extend(data_symbols);
+
+    let mut symbols = Vec::with_capacity(out_symbols.len());
+    let mut symbol_objects = HashMap::new();
+    for (address, size, unmangled_name, mangled_name, object) in out_symbols {
+        let address = match address {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let name = mangled_name
+            .map(|(_, mangled_name)| binfarce::demangle::SymbolName::demangle(mangled_name))
+            // Assume the Symbol record name is unmangled if we didn't find one.
+            // Note that unmangled names stored in PDB have a different format from
+            // one stored in binaries itself. Specifically they do not include hash
+            // and can have a bit different formatting.
+            // We also assume that a Legacy mangling scheme were used.
+            .unwrap_or_else(|| binfarce::demangle::SymbolName {
+                complete: unmangled_name.clone(),
+                trimmed: unmangled_name.clone(),
+                crate_name: None,
+                kind: binfarce::demangle::Kind::Legacy,
+            });
+
+        if let Some(object) = object {
+            symbol_objects.insert(name.complete.clone(), object);
+        }
+
+        symbols.push(SymbolData {
+            name,
+            address: address.0 as u64,
+            size,
+        });
+    }
+
+    let d = Data {
+        symbols,
+        file_size: 0,
+        text_size: section.size(),
+        section_name: Some(section.name.clone()),
+        sections: Vec::new(),
+        memory: None,
+        symbol_objects,
+        source_files: line_ranges.resolve(),
+        warnings: Vec::new(),
+        arch: None,
+    };
+
+    Ok(d)
+}
+
+/// Collects code ranges of functions and inline sites from a module line table.
+fn collect_pdb_lines(
+    info: &pdb::ModuleInfo<'_>,
+    strings: &pdb::StringTable<'_>,
+    address_map: &pdb::AddressMap<'_>,
+    section_range: &std::ops::Range<u64>,
+    ranges: &mut sources::LineRanges,
+) -> Result<(), pdb::Error> {
+    use pdb::FallibleIterator;
+
+    let program = info.line_program()?;
+    let mut push = |line: &pdb::LineInfo, depth: u32| {
+        let start = match line.offset.to_rva(address_map) {
+            Some(rva) => u64::from(rva.0),
+            None => return,
+        };
+
+        if !section_range.contains(&start) {
+            return;
+        }
+
+        let name = program
+            .get_file_info(line.file_index)
+            .and_then(|file| file.name.to_string_lossy(strings));
+        if let Ok(name) = name {
+            let end = start + u64::from(line.length.unwrap_or(0));
+            ranges.push(&name, start..end, depth);
+        }
+    };
+
+    let mut lines = program.lines();
+    while let Some(line) = lines.next()? {
+        push(&line, 0);
+    }
+
+    let mut inlinees = HashMap::new();
+    let mut iter = info.inlinees()?;
+    while let Some(inlinee) = iter.next()? {
+        inlinees.insert(inlinee.index(), inlinee);
+    }
+
+    // Inline site offsets are relative to the parent procedure
+    // and an inline site can be inside another one.
+    let mut depths = HashMap::new();
+    let mut proc_offset = None;
+    let mut symbols = info.symbols()?;
+    while let Some(symbol) = symbols.next()? {
+        match symbol.parse() {
+            Ok(pdb::SymbolData::Procedure(proc)) => {
+                proc_offset = Some(proc.offset);
+                depths.insert(symbol.index(), 0);
+            }
+            Ok(pdb::SymbolData::InlineSite(site)) => {
+                let parent_depth = site.parent.and_then(|index| depths.get(&index).cloned());
+                let depth = parent_depth.unwrap_or(0) + 1;
+                depths.insert(symbol.index(), depth);
+
+                if let (Some(offset), Some(inlinee)) = (proc_offset, inlinees.get(&site.inlinee)) {
+                    let mut lines = inlinee.lines(offset, &site);
+                    while let Some(line) = lines.next()? {
+                        push(&line, depth);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_pe_data(
+    path: &path::Path,
+    data: &[u8],
+    section_name: &str,
+    pdb_path: Option<&path::Path>,
+) -> Result<Data, Error> {
+    let file = pe::parse(data).ok_or_else(|| Error::UnsupportedFileFormat(path.to_owned()))?;
+    let section = file.find_section(section_name).ok_or(Error::ParsingError(
+        binfarce::ParseError::SymbolsSectionIsMissing,
+    ))?;
+
+    // An executable built with MSVC doesn't have any symbols.
+    if !file.has_symbols() {
+        let pdb_path = find_pdb(path, &file, pdb_path)?;
+        collect_pdb_data(&pdb_path, section)
+    } else {
+        Ok(Data {
+            symbols: file.symbols(section),
+            file_size: 0,
+            text_size: section.size(),
+            section_name: Some(section.name.clone()),
+            sections: Vec::new(),
+            memory: None,
+            symbol_objects: HashMap::new(),
+            source_files: Vec::new(),
+            warnings: Vec::new(),
+            arch: None,
+        })
+    }
+}
+
+pub struct Methods {
+    pub has_filter: bool,
+    pub filter_out_size: u64,
+    pub filter_out_len: usize,
+    pub methods: Vec<Method>,
+}
+
+pub struct Method {
+    pub name: String,
+    pub crate_name: String,
+    pub size: u64,
+}
+
+/// Returns the biggest functions.
+pub fn filter_methods(d: &mut CrateData, options: &Options) -> Methods {
+    d.data.symbols.sort_by_key(|v| v.size);
+
+    let dd = &d.data;
+    let n = if options.n == 0 {
+        dd.symbols.len()
+    } else {
+        options.n
+    };
+
+    let mut methods = Vec::with_capacity(n);
+
+    enum FilterBy {
+        None,
+        Crate(String),
+        #[cfg(feature = "regex-filter")]
+        Regex(regex::Regex),
+        #[cfg(not(feature = "regex-filter"))]
+        Substring(String),
+    }
+
+    let filter = if let Some(ref text) = options.filter {
+        if d.std_crates.contains(text) || d.dep_crates.contains(text) {
+            FilterBy::Crate(text.clone())
+        } else {
+            #[cfg(feature = "regex-filter")]
+            {
+                match regex::Regex::new(text) {
+                    Ok(re) => FilterBy::Regex(re),
+                    // Reported by `has_filter` being unset.
+                    Err(_) => FilterBy::None,
+                }
+            }
+
+            #[cfg(not(feature = "regex-filter"))]
+            {
+                FilterBy::Substring(text.clone())
+            }
+        }
+    } else {
+        FilterBy::None
+    };
+
+    let has_filter = !matches!(filter, FilterBy::None);
+
+    let mut filter_out_size = 0;
+    let mut filter_out_len = 0;
+
+    let crate_names = {
+        let d: &CrateData = d;
+        parallel::map(&dd.symbols, |sym| {
+            crate_name::from_sym(d, options, &sym.name)
+        })
+    };
+
+    for (sym, (mut crate_name, is_exact)) in dd.symbols.iter().zip(crate_names).rev() {
+        if !is_exact {
+            crate_name.push('?');
+        }
+
+        let name = if options.full_fn {
+            sym.name.complete.clone()
+        } else {
+            sym.name.trimmed.clone()
+        };
+
+        match filter {
+            FilterBy::None => {}
+            FilterBy::Crate(ref crate_name_f) => {
+                if crate_name_f != &crate_name {
+                    continue;
+                }
+            }
+            #[cfg(feature = "regex-filter")]
+            FilterBy::Regex(ref re) => {
+                if !re.is_match(&name) {
+                    continue;
+                }
+            }
+            #[cfg(not(feature = "regex-filter"))]
+            FilterBy::Substring(ref s) => {
+                if !name.contains(s) {
+                    continue;
+                }
+            }
+        }
+
+        filter_out_len += 1;
+
+        if n == 0 || methods.len() < n {
+            methods.push(Method {
+                name,
+                crate_name,
+                size: sym.size,
+            })
+        } else {
+            filter_out_size += sym.size;
+        }
+    }
+
+    Methods {
+        has_filter,
+        filter_out_size,
+        filter_out_len,
+        methods,
+    }
+}
+
+pub struct Crates {
+    pub filter_out_size: u64,
+    pub filter_out_len: usize,
+    pub crates: Vec<Crate>,
+}
+
+pub struct Crate {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Returns a crate name of a symbol and whether it's an exact match.
+pub fn crate_of(d: &CrateData, options: &Options, sym: &SymbolName) -> (String, bool) {
+    crate_name::from_sym(d, options, sym)
+}
+
+/// Returns crate names of all symbols and whether they are exact matches.
+pub fn symbol_crates(d: &CrateData, options: &Options) -> Vec<(String, bool)> {
+    parallel::map(&d.data.symbols, |sym| {
+        crate_name::from_sym(d, options, &sym.name)
+    })
+}
+
+/// Returns the total size of symbols per crate.
+pub fn crate_sizes(d: &CrateData, options: &Options) -> HashMap<String, u64> {
+    let mut sizes = HashMap::new();
+
+    let crate_names = symbol_crates(d, options);
+    for (sym, (crate_name, _)) in d.data.symbols.iter().zip(crate_names) {
+        if let Some(v) = sizes.get(&crate_name).cloned() {
+            sizes.insert(crate_name.to_string(), v + sym.size);
+        } else {
+            sizes.insert(crate_name.to_string(), sym.size);
+        }
+    }
+
+    sizes
+}
+
+/// Returns size changes between two `crate_sizes` results, the biggest ones first.
+///
+/// Crates that are gone completely have a negative change equal to their size.
+pub fn diff_sizes(old: &HashMap<String, u64>, new: &HashMap<String, u64>) -> Vec<(String, i64)> {
+    let mut changes = Vec::new();
+    for (name, size) in new {
+        let old_size = old.get(name).cloned().unwrap_or(0);
+        changes.push((name.clone(), *size as i64 - old_size as i64));
+    }
+
+    for (name, size) in old {
+        if !new.contains_key(name) {
+            changes.push((name.clone(), -(*size as i64)));
+        }
+    }
+
+    changes.retain(|v| v.1 != 0);
+    changes.sort_by(|a, b| b.1.abs().cmp(&a.1.abs()).then(a.0.cmp(&b.0)));
+    changes
+}
+
+/// Returns the biggest crates.
+pub fn filter_crates(d: &mut CrateData, options: &Options) -> Crates {
+    let mut crates = Vec::new();
+
+    let sizes = crate_sizes(d, options);
+
+    let mut list: Vec<(&String, &u64)> = sizes.iter().collect();
+    list.sort_by_key(|v| v.1);
+
+    let n = if options.n == 0 {
+        list.len()
+    } else {
+        options.n
+    };
+    for &(k, v) in list.iter().rev().take(n) {
+        crates.push(Crate {
+            name: k.clone(),
+            size: *v,
+        });
+    }
+
+    let mut filter_out_size = 0;
+    if n < list.len() {
+        for &(_, v) in list.iter().rev().skip(n) {
+            filter_out_size += *v;
+        }
+    }
+
+    Crates {
+        filter_out_size,
+        filter_out_len: list.len() - crates.len(),
+        crates,
+    }
+}
@@ -0,0 +1,533 @@
+//! Linker map files parsing.
+//!
+//! Supports the GNU ld (`-Map`), LLVM lld (`-Map`) and MSVC link.exe (`/MAP`) formats.
+
+use std::collections::HashMap;
+
+use binfarce::demangle::{SymbolData, SymbolName};
+
+use crate::crate_name;
+
+pub struct InputSection {
+    /// Output section name, like `.text`.
+    pub output_section: String,
+    /// Input section name, like `.text._ZN4core3fmt5write17h...E`.
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    /// Object file or an archive member, like `libcore-xxx.rlib(core-xxx.core.xxx-cgu.0.rcgu.o)`.
+    pub file: String,
+    /// Symbols defined in this input section.
+    pub symbols: Vec<String>,
+}
+
+pub fn parse(text: &str) -> Vec<InputSection> {
+    let is_lld = text
+        .lines()
+        .next()
+        .map(|line| {
+            matches!(
+                line.split_whitespace().next(),
+                Some("VMA") | Some("Address")
+            )
+        })
+        .unwrap_or(false);
+
+    if is_lld {
+        parse_lld(text)
+    } else if text.contains("Publics by Value") {
+        parse_msvc(text)
+    } else {
+        parse_gnu(text)
+    }
+}
+
+/// Converts input sections from the specified output section into symbols.
+///
+/// Returns symbols and their object files.
+pub fn collect_symbols(
+    sections: &[InputSection],
+    section_name: &str,
+) -> (Vec<SymbolData>, HashMap<String, String>) {
+    let mut symbols = Vec::new();
+    let mut objects = HashMap::new();
+    for input in sections {
+        if input.output_section != section_name || input.size == 0 {
+            continue;
+        }
+
+        // With `-ffunction-sections`, which is the default for Rust,
+        // each function has its own section, named after the mangled symbol name.
+        let name = match input.name.strip_prefix(section_name) {
+            Some(name) if name.len() > 1 && name.starts_with('.') => {
+                let name = &name[1..];
+                // GCC-style hot/cold splitting, like `.text.unlikely.<symbol>`.
+                let name = ["unlikely.", "hot.", "startup.", "exit."]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .unwrap_or(name);
+                name.to_string()
+            }
+            _ => match input.symbols.first() {
+                Some(name) => name.clone(),
+                None => format!("{} {}", input.name, input.file),
+            },
+        };
+
+        let name = SymbolName::demangle(&name);
+        objects.insert(name.complete.clone(), input.file.clone());
+        symbols.push(SymbolData {
+            name,
+            address: input.address,
+            size: input.size,
+        });
+    }
+
+    (symbols, objects)
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(s, 16).ok()
+}
+
+// Example:
+//
+//              VMA              LMA     Size Align Out     In      Symbol
+//          8000000          8000000      400     4 .vector_table
+//          8000000          8000000        8     1         app-xxx.app.xxx-cgu.0.rcgu.o:(.vector_table.reset_vector)
+//          8000000          8000000        0     1                 __RESET_VECTOR
+fn parse_lld(text: &str) -> Vec<InputSection> {
+    let mut lines = text.lines();
+    let header = match lines.next() {
+        Some(v) => v,
+        None => return Vec::new(),
+    };
+
+    let columns: Vec<&str> = header.split_whitespace().collect();
+    let numbers_count = columns.iter().position(|c| *c == "Out").unwrap_or(4);
+    let size_idx = columns.iter().position(|c| *c == "Size").unwrap_or(2);
+    let in_col = header.find(" In ").map(|v| v + 1).unwrap_or(usize::MAX);
+    let symbol_col = header.find("Symbol").unwrap_or(usize::MAX);
+
+    let mut sections: Vec<InputSection> = Vec::new();
+    let mut output_section = String::new();
+    for line in lines {
+        let mut numbers = Vec::with_capacity(numbers_count);
+        let mut rest = line;
+        for _ in 0..numbers_count {
+            let trimmed = rest.trim_start();
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            match parse_hex(&trimmed[..end]) {
+                Some(n) => numbers.push(n),
+                None => break,
+            }
+            rest = &trimmed[end..];
+        }
+
+        if numbers.len() != numbers_count {
+            continue;
+        }
+
+        let name = rest.trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        let column = line.len() - rest.trim_start().len();
+        if column >= symbol_col {
+            if let Some(section) = sections.last_mut() {
+                section.symbols.push(name.to_string());
+            }
+        } else if column >= in_col {
+            // `file.o:(.text.foo)`
+            let (file, section_name) = match name.rfind(":(") {
+                Some(idx) => (&name[..idx], name[idx + 2..].trim_end_matches(')')),
+                None => (name, ""),
+            };
+
+            sections.push(InputSection {
+                output_section: output_section.clone(),
+                name: section_name.to_string(),
+                address: numbers[0],
+                size: numbers[size_idx],
+                file: file.to_string(),
+                symbols: Vec::new(),
+            });
+        } else {
+            output_section = name.to_string();
+        }
+    }
+
+    sections
+}
+
+// Example:
+//
+// .text           0x0000000008000400     0x1234
+//  *(.text .text.*)
+//  .text.main     0x0000000008000400       0x40 target/.../deps/app-xxx.app.xxx-cgu.0.rcgu.o
+//                 0x0000000008000400                main
+//  .text._ZN4core3fmt5write17h...E
+//                 0x0000000008000440      0x300 .../libcore-xxx.rlib(core-xxx.core.xxx-cgu.0.rcgu.o)
+fn parse_gnu(text: &str) -> Vec<InputSection> {
+    enum Pending {
+        None,
+        Output(String),
+        Input(String),
+    }
+
+    let mut sections: Vec<InputSection> = Vec::new();
+    let mut output_section = String::new();
+    let mut pending = Pending::None;
+    let mut started = false;
+
+    for line in text.lines() {
+        if !started {
+            started = line.starts_with("Linker script and memory map");
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let is_hex = |idx: usize| tokens.get(idx).and_then(|s| parse_hex(s)).is_some();
+
+        if !line.starts_with(' ') {
+            // An output section.
+            pending = Pending::None;
+            if tokens.len() == 1 {
+                pending = Pending::Output(tokens[0].to_string());
+            } else if is_hex(1) {
+                output_section = tokens[0].to_string();
+            }
+        } else if !line[1..].starts_with(' ') {
+            // An input section.
+            pending = Pending::None;
+            let name = tokens[0];
+            if name.starts_with('*') {
+                // Input section patterns and `*fill*`.
+                continue;
+            }
+
+            if tokens.len() == 1 {
+                pending = Pending::Input(name.to_string());
+            } else if tokens.len() >= 4 && is_hex(1) && is_hex(2) {
+                push_gnu_section(&mut sections, &output_section, name, line, &tokens);
+            }
+        } else {
+            match std::mem::replace(&mut pending, Pending::None) {
+                Pending::Input(name) => {
+                    if tokens.len() >= 3 && is_hex(0) && is_hex(1) {
+                        let mut full = vec![name.as_str()];
+                        full.extend_from_slice(&tokens);
+                        push_gnu_section(&mut sections, &output_section, &name, line, &full);
+                    }
+                }
+                Pending::Output(name) => {
+                    if is_hex(0) {
+                        output_section = name;
+                    }
+                }
+                Pending::None => {
+                    // A symbol, unless it's an assignment like `. = ALIGN (0x4)`.
+                    if tokens.len() == 2 && is_hex(0) && !line.contains('=') {
+                        if let Some(section) = sections.last_mut() {
+                            section.symbols.push(tokens[1].to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    sections
+}
+
+fn push_gnu_section(
+    sections: &mut Vec<InputSection>,
+    output_section: &str,
+    name: &str,
+    line: &str,
+    tokens: &[&str],
+) {
+    let address = parse_hex(tokens[1]).unwrap_or(0);
+    let size = parse_hex(tokens[2]).unwrap_or(0);
+
+    // The file path may contain spaces, so take the rest of the line.
+    let after_address = line
+        .find(tokens[1])
+        .map(|idx| idx + tokens[1].len())
+        .unwrap_or(0);
+    let file = match line[after_address..].find(tokens[2]) {
+        Some(idx) => line[after_address + idx + tokens[2].len()..].trim(),
+        None => tokens[3],
+    };
+
+    sections.push(InputSection {
+        output_section: output_section.to_string(),
+        name: name.to_string(),
+        address,
+        size,
+        file: file.to_string(),
+        symbols: Vec::new(),
+    });
+}
+
+// Example:
+//
+//  Start         Length     Name                   Class
+//  0001:00000000 00012345H .text$mn                CODE
+//  ...
+//   Address         Publics by Value              Rva+Base               Lib:Object
+//
+//  0001:00000040       _ZN4core3fmt5write17h...E  0000000140001040 f   libcore-xxx.rlib:core-xxx.core.xxx-cgu.0.rcgu.o
+//
+// Symbols don't have sizes, so we have to use the distance to the next symbol.
+fn parse_msvc(text: &str) -> Vec<InputSection> {
+    // Section index -> (name, end offset).
+    let mut section_info: HashMap<u16, (String, u64)> = HashMap::new();
+    let mut publics: Vec<(u16, u64, String, String)> = Vec::new(); // section, offset, name, file
+
+    let parse_address = |s: &str| -> Option<(u16, u64)> {
+        let mut parts = s.splitn(2, ':');
+        let section = u16::from_str_radix(parts.next()?, 16).ok()?;
+        let offset = u64::from_str_radix(parts.next()?, 16).ok()?;
+        Some((section, offset))
+    };
+
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (section, offset) = match tokens.first().and_then(|s| parse_address(s)) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        // Section contributions, like `0001:00000000 00012345H .text$mn CODE`.
+        if let Some(length) = tokens.get(1).and_then(|s| s.strip_suffix('H')) {
+            if let (Ok(length), Some(name)) = (u64::from_str_radix(length, 16), tokens.get(2)) {
+                let name = name.split('$').next().unwrap_or(name);
+                let entry = section_info
+                    .entry(section)
+                    .or_insert_with(|| (name.to_string(), 0));
+                entry.1 = entry.1.max(offset + length);
+                continue;
+            }
+        }
+
+        // Public and static symbols.
+        if tokens.len() >= 4 && section != 0 {
+            let name = tokens[1];
+            let file = tokens[tokens.len() - 1];
+            // `libfoo.rlib:foo.o` -> `libfoo.rlib(foo.o)`
+            let file = match file.rfind(':') {
+                Some(idx) if idx > 1 => format!("{}({})", &file[..idx], &file[idx + 1..]),
+                _ => file.to_string(),
+            };
+            publics.push((section, offset, name.to_string(), file));
+        }
+    }
+
+    publics.sort_by_key(|v| (v.0, v.1));
+    publics.dedup_by_key(|v| (v.0, v.1));
+
+    let mut sections = Vec::with_capacity(publics.len());
+    for (i, (section, offset, name, file)) in publics.iter().enumerate() {
+        let (output_section, end) = match section_info.get(section) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let next = match publics.get(i + 1) {
+            Some(next) if next.0 == *section => next.1,
+            _ => *end,
+        };
+
+        sections.push(InputSection {
+            output_section: output_section.clone(),
+            name: String::new(),
+            address: *offset,
+            size: next.saturating_sub(*offset),
+            file: file.clone(),
+            symbols: vec![name.clone()],
+        });
+    }
+
+    sections
+}
+
+/// Returns a crate name from an object file path.
+///
+/// Rust object files and archives have a `name-hash` format,
+/// like `libserde-1a2b3c.rlib` or `app-1a2b3c.app.4d5e-cgu.0.rcgu.o`.
+/// Object files of binaries can have no hash, like `app.app.4d5e-cgu.0.rcgu.o`.
+pub fn crate_from_file(file: &str) -> String {
+    let (archive, member) = match (file.rfind('('), file.ends_with(')')) {
+        (Some(idx), true) => (&file[..idx], Some(&file[idx + 1..file.len() - 1])),
+        _ => (file, None),
+    };
+
+    let file_name =
+        |path: &str| -> String { path.rsplit(['/', '\\']).next().unwrap_or(path).to_string() };
+
+    let archive_name = file_name(archive);
+    if let Some(stem) = archive_name.strip_suffix(".rlib") {
+        let stem = stem.strip_prefix("lib").unwrap_or(stem);
+        return stem.split('-').next().unwrap_or(stem).to_string();
+    }
+
+    let object_name = match member {
+        Some(member) => file_name(member),
+        None => archive_name.clone(),
+    };
+
+    if object_name.ends_with(".rcgu.o") {
+        return object_name
+            .split(['-', '.'])
+            .next()
+            .unwrap_or(&object_name)
+            .to_string();
+    }
+
+    if archive_name.is_empty() || archive_name.starts_with('<') {
+        return crate_name::UNKNOWN.to_string();
+    }
+
+    // A non-Rust library or object file, like `libc.a` or `crt0.o`.
+    archive_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LLD: &str = "             VMA              LMA     Size Align Out     In      Symbol
+          201000           201000       40    16 .text
+          201000           201000       30    16         /tmp/app-1a2b.app.3c4d-cgu.0.rcgu.o:(.text._ZN3app4main17h0123456789abcdefE)
+          201000           201000        0     1                 _ZN3app4main17h0123456789abcdefE
+          201030           201030       10    16         /rust/lib/libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o):(.text.memcpy)
+          201030           201030        0     1                 memcpy
+";
+
+    const GNU: &str = "\
+Archive member included to satisfy reference by file (symbol)
+
+Linker script and memory map
+
+.text           0x0000000008000400       0x70
+ *(.text .text.*)
+ .text.main     0x0000000008000400       0x40 target/deps/app-1a2b.app.3c4d-cgu.0.rcgu.o
+                0x0000000008000400                main
+ .text._ZN4core3fmt5write17h0123456789abcdefE
+                0x0000000008000440       0x30 /rust/lib/libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o)
+                0x0000000008000440                _ZN4core3fmt5write17h0123456789abcdefE
+                0x0000000008000470                . = ALIGN (0x4)
+ *fill*         0x0000000008000470        0x0
+";
+
+    const MSVC: &str = "\
+ app
+
+ Preferred load address is 0000000140000000
+
+ Start         Length     Name                   Class
+ 0001:00000000 00000100H .text$mn                CODE
+ 0002:00000000 00000020H .rdata                  DATA
+
+  Address         Publics by Value              Rva+Base               Lib:Object
+
+ 0000:00000000       __ImageBase                0000000140000000     <linker-defined>
+ 0001:00000000       main                       0000000140001000 f   app.app.3c4d-cgu.0.rcgu.o
+ 0001:00000040       _ZN4core3fmt5write17h0123456789abcdefE 0000000140001040 f   libcore-5e6f.rlib:core-5e6f.core.7a8b-cgu.0.rcgu.o
+";
+
+    #[test]
+    fn lld() {
+        let sections = parse(LLD);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].output_section, ".text");
+        assert_eq!(sections[0].name, ".text._ZN3app4main17h0123456789abcdefE");
+        assert_eq!(sections[0].address, 0x201000);
+        assert_eq!(sections[0].size, 0x30);
+        assert_eq!(sections[0].file, "/tmp/app-1a2b.app.3c4d-cgu.0.rcgu.o");
+        assert_eq!(sections[0].symbols, ["_ZN3app4main17h0123456789abcdefE"]);
+        assert_eq!(
+            sections[1].file,
+            "/rust/lib/libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o)"
+        );
+        assert_eq!(sections[1].symbols, ["memcpy"]);
+    }
+
+    #[test]
+    fn gnu() {
+        let sections = parse(GNU);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].output_section, ".text");
+        assert_eq!(sections[0].name, ".text.main");
+        assert_eq!(sections[0].address, 0x8000400);
+        assert_eq!(sections[0].size, 0x40);
+        assert_eq!(sections[0].symbols, ["main"]);
+
+        // A long section name is wrapped to the next line.
+        assert_eq!(
+            sections[1].name,
+            ".text._ZN4core3fmt5write17h0123456789abcdefE"
+        );
+        assert_eq!(sections[1].size, 0x30);
+        assert_eq!(
+            sections[1].file,
+            "/rust/lib/libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o)"
+        );
+        assert_eq!(
+            sections[1].symbols,
+            ["_ZN4core3fmt5write17h0123456789abcdefE"]
+        );
+    }
+
+    #[test]
+    fn msvc() {
+        let sections = parse(MSVC);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].output_section, ".text");
+        assert_eq!(sections[0].symbols, ["main"]);
+        assert_eq!(sections[0].size, 0x40);
+        assert_eq!(sections[0].file, "app.app.3c4d-cgu.0.rcgu.o");
+        assert_eq!(crate_from_file(&sections[0].file), "app");
+
+        // The last symbol ends with its section.
+        assert_eq!(sections[1].size, 0xc0);
+        assert_eq!(
+            sections[1].file,
+            "libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o)"
+        );
+    }
+
+    #[test]
+    fn symbols() {
+        let (symbols, objects) = collect_symbols(&parse(GNU), ".text");
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].name.trimmed, "main");
+        assert_eq!(symbols[1].name.trimmed, "core::fmt::write");
+        assert_eq!(
+            objects.get(&symbols[1].name.complete).map(String::as_str),
+            Some("/rust/lib/libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o)")
+        );
+    }
+
+    #[test]
+    fn crate_names() {
+        assert_eq!(
+            crate_from_file("/tmp/app-1a2b.app.3c4d-cgu.0.rcgu.o"),
+            "app"
+        );
+        assert_eq!(
+            crate_from_file("/rust/lib/libcore-5e6f.rlib(core-5e6f.core.7a8b-cgu.0.rcgu.o)"),
+            "core"
+        );
+        assert_eq!(crate_from_file("app.app.3c4d-cgu.0.rcgu.o"), "app");
+        assert_eq!(crate_from_file("/usr/lib/libc.a(memcpy.o)"), "libc.a");
+        assert_eq!(crate_from_file("<internal>"), crate_name::UNKNOWN);
+    }
+}
@@ -0,0 +1,271 @@
+//! A minimal Mach-O reader.
+//!
+//! Unlike `binfarce`, it can read symbols from any section and supports dSYM bundles.
+
+use std::convert::TryInto;
+use std::ops::Range;
+use std::{fs, path};
+
+use binfarce::demangle::{SymbolData, SymbolName};
+
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+
+const LC_SEGMENT: u32 = 0x1;
+const LC_SYMTAB: u32 = 0x2;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_UUID: u32 = 0x1b;
+
+const N_STAB: u8 = 0xe0;
+const N_TYPE: u8 = 0x0e;
+const N_SECT: u8 = 0x0e;
+
+pub const DEFAULT_SECTION: &str = "__TEXT,__text";
+
+pub struct Section {
+    /// 1-based, like in symbols.
+    pub index: usize,
+    pub segment: String,
+    pub name: String,
+    pub address: u64,
+    pub offset: u32,
+    pub size: u64,
+}
+
+impl Section {
+    pub fn full_name(&self) -> String {
+        format!("{},{}", self.segment, self.name)
+    }
+
+    pub fn range(&self) -> Option<Range<usize>> {
+        let start = self.offset as usize;
+        let end = start.checked_add(self.size.try_into().ok()?)?;
+        Some(start..end)
+    }
+}
+
+struct Symtab {
+    offset: usize,
+    count: usize,
+    strings: Range<usize>,
+}
+
+pub struct MachO<'a> {
+    data: &'a [u8],
+    is_64_bit: bool,
+    pub sections: Vec<Section>,
+    pub uuid: Option<[u8; 16]>,
+    symtab: Option<Symtab>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn fixed_string(data: &[u8], offset: usize) -> Option<String> {
+    let data = data.get(offset..offset.checked_add(16)?)?;
+    let len = data.iter().position(|c| *c == 0).unwrap_or(data.len());
+    Some(String::from_utf8_lossy(&data[..len]).into_owned())
+}
+
+fn null_string(data: &[u8], offset: usize) -> Option<&str> {
+    let data = data.get(offset..)?;
+    let len = data.iter().position(|c| *c == 0)?;
+    std::str::from_utf8(&data[..len]).ok()
+}
+
+pub fn parse(data: &[u8]) -> Option<MachO<'_>> {
+    let is_64_bit = match read_u32(data, 0)? {
+        MH_MAGIC => false,
+        MH_MAGIC_64 => true,
+        _ => return None,
+    };
+
+    let commands_count = read_u32(data, 16)?;
+    let mut offset = if is_64_bit { 32 } else { 28 };
+
+    let mut sections = Vec::new();
+    let mut uuid = None;
+    let mut symtab = None;
+    for _ in 0..commands_count {
+        let kind = read_u32(data, offset)?;
+        let size = read_u32(data, offset + 4)? as usize;
+        if size < 8 {
+            return None;
+        }
+
+        match kind {
+            LC_SEGMENT | LC_SEGMENT_64 => {
+                let is_64 = kind == LC_SEGMENT_64;
+                // Skip segname, addresses, sizes and protections.
+                let (count_offset, header_size, section_size) =
+                    if is_64 { (64, 72, 80) } else { (48, 56, 68) };
+
+                let count = read_u32(data, offset + count_offset)?;
+                let mut s = offset + header_size;
+                for _ in 0..count {
+                    let name = fixed_string(data, s)?;
+                    let segment = fixed_string(data, s + 16)?;
+                    let (address, size, file_offset) = if is_64 {
+                        (
+                            read_u64(data, s + 32)?,
+                            read_u64(data, s + 40)?,
+                            read_u32(data, s + 48)?,
+                        )
+                    } else {
+                        (
+                            read_u32(data, s + 32)?.into(),
+                            read_u32(data, s + 36)?.into(),
+                            read_u32(data, s + 40)?,
+                        )
+                    };
+
+                    sections.push(Section {
+                        index: sections.len() + 1,
+                        segment,
+                        name,
+                        address,
+                        offset: file_offset,
+                        size,
+                    });
+                    s += section_size;
+                }
+            }
+            LC_SYMTAB => {
+                let strings_offset = read_u32(data, offset + 16)? as usize;
+                let strings_size = read_u32(data, offset + 20)? as usize;
+                symtab = Some(Symtab {
+                    offset: read_u32(data, offset + 8)? as usize,
+                    count: read_u32(data, offset + 12)? as usize,
+                    strings: strings_offset..strings_offset.checked_add(strings_size)?,
+                });
+            }
+            LC_UUID => {
+                let bytes = data.get(offset + 8..offset + 24)?;
+                uuid = bytes.try_into().ok();
+            }
+            _ => {}
+        }
+
+        offset = offset.checked_add(size)?;
+    }
+
+    Some(MachO {
+        data,
+        is_64_bit,
+        sections,
+        uuid,
+        symtab,
+    })
+}
+
+impl MachO<'_> {
+    /// Finds a section by a `SEGMENT,section` or just `section` name.
+    pub fn find_section(&self, name: &str) -> Option<&Section> {
+        match name.split_once(',') {
+            Some((segment, name)) => self
+                .sections
+                .iter()
+                .find(|s| s.segment == segment && s.name == name),
+            None => self.sections.iter().find(|s| s.name == name),
+        }
+    }
+
+    /// Returns symbols from the specified section.
+    ///
+    /// Mach-O doesn't store symbol sizes, so we have to use the distance to the next symbol.
+    pub fn symbols(&self, section: &Section) -> Vec<SymbolData> {
+        let symtab = match self.symtab {
+            Some(ref v) => v,
+            None => return Vec::new(),
+        };
+
+        let strings = match self.data.get(symtab.strings.clone()) {
+            Some(v) => v,
+            None => return Vec::new(),
+        };
+
+        let entry_size = if self.is_64_bit { 16 } else { 12 };
+        let mut raw_symbols = Vec::new();
+        for i in 0..symtab.count {
+            let offset = symtab.offset + i * entry_size;
+            let (name_offset, kind, section_index) = match (
+                read_u32(self.data, offset),
+                self.data.get(offset + 4),
+                self.data.get(offset + 5),
+            ) {
+                (Some(a), Some(b), Some(c)) => (a, *b, *c),
+                _ => break,
+            };
+
+            let address = if self.is_64_bit {
+                read_u64(self.data, offset + 8)
+            } else {
+                read_u32(self.data, offset + 8).map(u64::from)
+            };
+
+            let address = match address {
+                Some(v) => v,
+                None => break,
+            };
+
+            // Ignore debug entries and symbols from other sections.
+            if kind & N_STAB != 0
+                || kind & N_TYPE != N_SECT
+                || section_index as usize != section.index
+                || name_offset == 0
+            {
+                continue;
+            }
+
+            raw_symbols.push((address, name_offset as usize));
+        }
+
+        raw_symbols.sort_by_key(|v| v.0);
+
+        let end = section.address + section.size;
+        let mut symbols = Vec::with_capacity(raw_symbols.len());
+        for (i, &(address, name_offset)) in raw_symbols.iter().enumerate() {
+            // Next symbol can have the same address as the current one,
+            // so we have to find the one that has a different address.
+            let next = raw_symbols[i..]
+                .iter()
+                .map(|v| v.0)
+                .find(|a| *a != address)
+                .unwrap_or(end);
+
+            if let Some(name) = null_string(strings, name_offset) {
+                symbols.push(SymbolData {
+                    name: SymbolName::demangle(name),
+                    address,
+                    size: next.saturating_sub(address),
+                });
+            }
+        }
+
+        symbols
+    }
+}
+
+/// Returns DWARF files from a `<binary>.dSYM` bundle next to the binary.
+pub fn find_dsym_files(path: &path::Path) -> Vec<path::PathBuf> {
+    let mut bundle = path.as_os_str().to_owned();
+    bundle.push(".dSYM");
+    let dir = path::Path::new(&bundle).join("Contents/Resources/DWARF");
+
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            files.push(entry.path());
+        }
+    }
+
+    files.sort();
+    files
+}
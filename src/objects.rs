@@ -0,0 +1,69 @@
+//! Per object file sizes.
+//!
+//! Object files are known only from a linker map or a PDB file.
+
+use std::collections::HashMap;
+
+use crate::{crate_name, linker_map, CrateData, Options};
+
+pub struct Objects {
+    pub filter_out_size: u64,
+    pub filter_out_len: usize,
+    pub objects: Vec<Object>,
+    pub is_empty: bool, // no object files info at all
+}
+
+pub struct Object {
+    pub file: String,
+    pub crate_name: String,
+    pub size: u64,
+}
+
+/// Returns the biggest object files.
+pub fn collect(d: &CrateData, options: &Options) -> Objects {
+    let mut sizes: HashMap<&str, u64> = HashMap::new();
+    for sym in &d.data.symbols {
+        let file = d
+            .data
+            .symbol_objects
+            .get(&sym.name.complete)
+            .map(String::as_str)
+            .unwrap_or(crate_name::UNKNOWN);
+        *sizes.entry(file).or_insert(0) += sym.size;
+    }
+
+    let mut list: Vec<(&str, u64)> = sizes.into_iter().collect();
+    list.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let n = if options.n == 0 {
+        list.len()
+    } else {
+        options.n
+    };
+    let mut objects = Vec::new();
+    for &(file, size) in list.iter().take(n) {
+        let crate_name = if file == crate_name::UNKNOWN {
+            String::new()
+        } else {
+            let name = linker_map::crate_from_file(file);
+            if !options.split_std && d.std_crates.contains(&name) {
+                "std".to_string()
+            } else {
+                name
+            }
+        };
+
+        objects.push(Object {
+            file: file.to_string(),
+            crate_name,
+            size,
+        });
+    }
+
+    Objects {
+        filter_out_size: list.iter().skip(n).map(|v| v.1).sum(),
+        filter_out_len: list.len().saturating_sub(n),
+        objects,
+        is_empty: d.data.symbol_objects.is_empty(),
+    }
+}
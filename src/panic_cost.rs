@@ -0,0 +1,273 @@
+//! Size of the panicking and formatting machinery.
+
+use crate::CrateData;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Category {
+    Unwinding,
+    Backtrace,
+    Panicking,
+    Formatting,
+}
+
+impl Category {
+    const ALL: &'static [Category] = &[
+        Category::Panicking,
+        Category::Formatting,
+        Category::Backtrace,
+        Category::Unwinding,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Unwinding => "Unwinding",
+            Category::Backtrace => "Backtraces and symbolization",
+            Category::Panicking => "Panicking",
+            Category::Formatting => "Formatting",
+        }
+    }
+
+    pub fn json_name(self) -> &'static str {
+        match self {
+            Category::Unwinding => "unwinding",
+            Category::Backtrace => "backtrace",
+            Category::Panicking => "panicking",
+            Category::Formatting => "formatting",
+        }
+    }
+}
+
+// The order matters, since the first match wins.
+// For example, `<core::panic::PanicInfo as core::fmt::Display>::fmt`
+// is a part of the panicking machinery and not just formatting.
+//
+// Patterns are prefixes of a path from a crate root or of a symbol without a path,
+// except for `__rust_` ones, which can start any path segment. See `matches`.
+const PATTERNS: &[(Category, &[&str])] = &[
+    (
+        Category::Unwinding,
+        &[
+            "_Unwind_",
+            "__gcc_personality",
+            "rust_eh_personality",
+            "__rust_start_panic",
+            "__rust_panic_cleanup",
+            "panic_unwind::",
+            "unwind::",
+            "__cxa_",
+            "__register_frame",
+            "__deregister_frame",
+            "std::personality::",
+            "std::sys::personality::",
+        ],
+    ),
+    (
+        Category::Backtrace,
+        &[
+            "__rust_begin_short_backtrace",
+            "__rust_end_short_backtrace",
+            "std::backtrace::",
+            "std::backtrace_rs::",
+            "std::sys::backtrace::",
+            "std::sys_common::backtrace::",
+            "backtrace::",
+            "gimli::",
+            "addr2line::",
+            "rustc_demangle::",
+            "miniz_oxide::",
+            "object::",
+            "adler::",
+            "adler2::",
+        ],
+    ),
+    (
+        Category::Panicking,
+        &[
+            "core::panicking::",
+            "std::panicking::",
+            "core::panic::",
+            "std::panic::",
+            "rust_begin_unwind",
+            "rust_panic",
+            "core::result::unwrap_failed",
+            "core::option::unwrap_failed",
+            "core::option::expect_failed",
+            "core::slice::index::slice_index_fail",
+            "core::slice::index::slice_start_index_len_fail",
+            "core::slice::index::slice_end_index_len_fail",
+            "core::slice::index::slice_index_order_fail",
+            "core::slice::index::slice_start_index_overflow_fail",
+            "core::slice::index::slice_end_index_overflow_fail",
+            "core::str::slice_error_fail",
+            "core::cell::panic_already",
+            "alloc::raw_vec::capacity_overflow",
+            "alloc::raw_vec::handle_error",
+            "alloc::alloc::handle_alloc_error",
+            "std::alloc::default_alloc_error_hook",
+        ],
+    ),
+    (
+        Category::Formatting,
+        &[
+            "core::fmt::",
+            "alloc::fmt::",
+            "core::num::flt2dec::",
+            "core::num::fmt::",
+            "std::io::Write::write_fmt",
+            "std::io::default_write_fmt",
+        ],
+    ),
+];
+
+// Exception tables that are used only by landing pads.
+const LSDA_SECTIONS: &[&str] = &[".gcc_except_table", "__TEXT,__gcc_except_tab"];
+
+// Unwinding tables. Some of them are still required for backtraces with `panic = "abort"`.
+const UNWIND_SECTIONS: &[&str] = &[
+    ".eh_frame",
+    ".eh_frame_hdr",
+    ".gcc_except_table",
+    "__TEXT,__eh_frame",
+    "__TEXT,__unwind_info",
+    "__TEXT,__gcc_except_tab",
+    ".pdata",
+    ".xdata",
+];
+
+/// Checks that a section is kept with `panic = "abort"`.
+///
+/// Only landing pads metadata goes away, while unwinding tables are still used for backtraces.
+pub fn is_kept_with_abort(section: &str) -> bool {
+    !LSDA_SECTIONS.contains(&section)
+}
+
+pub struct Report {
+    pub categories: Vec<(Category, u64, usize)>, // category, size, symbols count
+    pub sections: Vec<(String, u64)>,
+    pub abort_savings: u64,
+    pub immediate_abort_savings: u64,
+}
+
+fn category(name: &str) -> Option<Category> {
+    for (category, patterns) in PATTERNS {
+        if patterns.iter().any(|p| matches(name, p)) {
+            return Some(*category);
+        }
+    }
+
+    None
+}
+
+/// Checks that a demangled name contains a pattern at the start of a path,
+/// so `object::` doesn't match `my_crate::object::` and `std::` doesn't match `no_std::`.
+///
+/// Paths can start anywhere in a name, like in `<&gimli::Unit as core::fmt::Debug>::fmt`.
+/// Symbols reserved by std, like `__rust_begin_short_backtrace`, can start any path segment
+/// as well, while `rust_panic` doesn't match `my_crate::rust_panic_hook`.
+fn matches(name: &str, pattern: &str) -> bool {
+    let is_segment = pattern.starts_with("__rust_");
+    name.match_indices(pattern).any(|(idx, _)| {
+        let prefix = &name[..idx];
+        match prefix.chars().next_back() {
+            None => true,
+            Some(':') => is_segment && prefix.ends_with("::"),
+            Some(c) => !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'),
+        }
+    })
+}
+
+/// Estimates how much space panics and formatting take.
+pub fn collect(d: &CrateData) -> Report {
+    let mut categories: Vec<(Category, u64, usize)> =
+        Category::ALL.iter().map(|c| (*c, 0, 0)).collect();
+
+    for sym in &d.data.symbols {
+        if let Some(c) = category(&sym.name.trimmed) {
+            if let Some(item) = categories.iter_mut().find(|v| v.0 == c) {
+                item.1 += sym.size;
+                item.2 += 1;
+            }
+        }
+    }
+
+    let sections: Vec<(String, u64)> = d
+        .data
+        .sections
+        .iter()
+        .filter(|(name, size)| *size != 0 && UNWIND_SECTIONS.contains(&name.as_str()))
+        .cloned()
+        .collect();
+
+    let category_size = |c: Category| {
+        categories
+            .iter()
+            .find(|v| v.0 == c)
+            .map(|v| v.1)
+            .unwrap_or(0)
+    };
+
+    // `panic = "abort"` removes the unwinding runtime and landing pads metadata.
+    // Landing pads themselves are inlined into functions, so we cannot measure them.
+    let lsda_size: u64 = sections
+        .iter()
+        .filter(|(name, _)| LSDA_SECTIONS.contains(&name.as_str()))
+        .map(|v| v.1)
+        .sum();
+    let abort_savings = category_size(Category::Unwinding) + lsda_size;
+
+    // `panic_immediate_abort` removes all the machinery above, since nothing could panic anymore.
+    // Unwinding tables are still emitted by default, so only landing pads metadata goes away.
+    let immediate_abort_savings = categories.iter().map(|v| v.1).sum::<u64>() + lsda_size;
+
+    Report {
+        categories,
+        sections,
+        abort_savings,
+        immediate_abort_savings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchored_patterns() {
+        assert_eq!(
+            category("gimli::read::Dwarf::load"),
+            Some(Category::Backtrace)
+        );
+        assert_eq!(
+            category("<&gimli::read::Unit as core::fmt::Debug>::fmt"),
+            Some(Category::Backtrace)
+        );
+        assert_eq!(
+            category("std::sys::backtrace::__rust_begin_short_backtrace"),
+            Some(Category::Backtrace)
+        );
+        assert_eq!(
+            category("core::slice::index::slice_end_index_len_fail"),
+            Some(Category::Panicking)
+        );
+        assert_eq!(category("_Unwind_Resume"), Some(Category::Unwinding));
+        assert_eq!(category("rust_panic"), Some(Category::Panicking));
+        assert_eq!(
+            category("std::panicking::rust_panic_with_hook"),
+            Some(Category::Panicking)
+        );
+
+        assert_eq!(category("my_crate::object::parse"), None);
+        assert_eq!(category("my_crate::backtrace_printer"), None);
+        assert_eq!(category("my_crate::unwind::run"), None);
+        assert_eq!(category("my_crate::rust_panic_hook"), None);
+        assert_eq!(category("my_crate::_Unwind_wrapper"), None);
+        assert_eq!(category("my_crate::ffi::__cxa_demangle"), None);
+        assert_eq!(category("<my_crate::Writer>::write_fmt"), None);
+        assert_eq!(
+            category(
+                "<core::ops::range::Range<usize> as core::slice::index::SliceIndex<[u8]>>::index"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! A minimal PE reader.
+//!
+//! Unlike `binfarce`, it enumerates all sections and can read symbols from any of them.
+
+use std::convert::TryInto;
+use std::ops::Range;
+
+use binfarce::demangle::{SymbolData, SymbolName};
+
+const PE_POINTER_OFFSET: usize = 0x3c;
+const COFF_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const COFF_SYMBOL_SIZE: usize = 18;
+
+const IMAGE_SCN_CNT_CODE: u32 = 0x20;
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+const IMAGE_DIRECTORY_ENTRY_DEBUG: usize = 6;
+const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+const DEBUG_DIRECTORY_SIZE: usize = 28;
+
+const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+const IMAGE_SYM_CLASS_STATIC: u8 = 3;
+const IMAGE_SYM_DTYPE_SHIFT: usize = 4;
+const IMAGE_SYM_DTYPE_FUNCTION: u16 = 2;
+
+pub struct Section {
+    /// 1-based, like in symbols.
+    pub number: usize,
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    raw_size: u32,
+    raw_offset: u32,
+    characteristics: u32,
+}
+
+impl Section {
+    /// Returns the size of the section in the file or in memory for uninitialized data.
+    pub fn size(&self) -> u64 {
+        if self.raw_size != 0 {
+            self.raw_size.into()
+        } else {
+            self.virtual_size.into()
+        }
+    }
+
+    pub fn rva_range(&self) -> Range<u64> {
+        let start = u64::from(self.virtual_address);
+        start..start + u64::from(self.virtual_size.max(self.raw_size))
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.characteristics & IMAGE_SCN_CNT_CODE != 0
+    }
+}
+
+/// A CodeView debug record, which links an executable to its PDB file.
+pub struct CodeView {
+    /// GUID fields, as they are stored in the file.
+    pub guid: (u32, u16, u16, [u8; 8]),
+    pub age: u32,
+    pub path: String,
+}
+
+impl CodeView {
+    /// Returns a directory name used by symbol stores, like `<GUID><age>`.
+    pub fn signature(&self) -> String {
+        let (d1, d2, d3, d4) = self.guid;
+        let d4: String = d4.iter().map(|b| format!("{:02X}", b)).collect();
+        format!("{:08X}{:04X}{:04X}{}{:X}", d1, d2, d3, d4, self.age)
+    }
+}
+
+pub struct Pe<'a> {
+    data: &'a [u8],
+    pub sections: Vec<Section>,
+    debug_directory: Option<(u32, u32)>, // rva, size
+    symbols_offset: usize,
+    symbols_count: usize,
+    strings_offset: usize,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn null_string(data: &[u8], offset: usize) -> Option<&str> {
+    let data = data.get(offset..)?;
+    let len = data.iter().position(|c| *c == 0)?;
+    std::str::from_utf8(&data[..len]).ok()
+}
+
+/// Returns a short name stored in place or a long name from the COFF string table.
+fn symbol_name(raw: &[u8], strings_offset: usize, data: &[u8]) -> Option<String> {
+    if raw.starts_with(&[0, 0, 0, 0]) {
+        let offset = read_u32(raw, 4)? as usize;
+        null_string(data, strings_offset.checked_add(offset)?).map(str::to_string)
+    } else {
+        let len = raw.iter().position(|c| *c == 0).unwrap_or(raw.len());
+        std::str::from_utf8(&raw[..len]).ok().map(str::to_string)
+    }
+}
+
+pub fn parse(data: &[u8]) -> Option<Pe<'_>> {
+    let pe_offset = read_u32(data, PE_POINTER_OFFSET)? as usize;
+    if data.get(pe_offset..pe_offset.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+
+    let coff = pe_offset + 4;
+    let sections_count = read_u16(data, coff + 2)? as usize;
+    let symbols_offset = read_u32(data, coff + 8)? as usize;
+    let symbols_count = read_u32(data, coff + 12)? as usize;
+    let optional_header_size = read_u16(data, coff + 16)? as usize;
+
+    // The string table follows the symbol table.
+    let strings_offset = symbols_count
+        .checked_mul(COFF_SYMBOL_SIZE)
+        .and_then(|size| symbols_offset.checked_add(size))?;
+
+    let optional_header = coff + COFF_HEADER_SIZE;
+    let directories = match read_u16(data, optional_header) {
+        Some(PE32_MAGIC) => Some(optional_header + 92),
+        Some(PE32_PLUS_MAGIC) => Some(optional_header + 108),
+        _ => None,
+    };
+
+    let debug_directory = directories.and_then(|offset| {
+        let count = read_u32(data, offset)? as usize;
+        if count <= IMAGE_DIRECTORY_ENTRY_DEBUG {
+            return None;
+        }
+
+        let entry = offset + 4 + IMAGE_DIRECTORY_ENTRY_DEBUG * 8;
+        Some((read_u32(data, entry)?, read_u32(data, entry + 4)?))
+    });
+
+    let mut sections = Vec::with_capacity(sections_count);
+    let mut offset = coff + COFF_HEADER_SIZE + optional_header_size;
+    for i in 0..sections_count {
+        let raw_name = data.get(offset..offset + 8)?;
+        let len = raw_name
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(raw_name.len());
+        let name = std::str::from_utf8(&raw_name[..len]).unwrap_or("");
+        let name = match name.strip_prefix('/') {
+            // Long section names, like `.debug_info`, are stored in the string table.
+            Some(index) => {
+                let index: usize = index.parse().ok()?;
+                strings_offset
+                    .checked_add(index)
+                    .and_then(|offset| null_string(data, offset))
+                    .unwrap_or("")
+                    .to_string()
+            }
+            None => name.to_string(),
+        };
+
+        sections.push(Section {
+            number: i + 1,
+            name,
+            virtual_size: read_u32(data, offset + 8)?,
+            virtual_address: read_u32(data, offset + 12)?,
+            raw_size: read_u32(data, offset + 16)?,
+            raw_offset: read_u32(data, offset + 20)?,
+            characteristics: read_u32(data, offset + 36)?,
+        });
+        offset += SECTION_HEADER_SIZE;
+    }
+
+    Some(Pe {
+        data,
+        sections,
+        debug_directory,
+        symbols_offset,
+        symbols_count,
+        strings_offset,
+    })
+}
+
+impl Pe<'_> {
+    pub fn find_section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.sections.iter().find_map(|s| {
+            let delta = rva.checked_sub(s.virtual_address)?;
+            if delta >= s.raw_size {
+                return None;
+            }
+
+            delta
+                .checked_add(s.raw_offset)
+                .map(|offset| offset as usize)
+        })
+    }
+
+    /// Returns the CodeView record from the debug directory.
+    pub fn codeview(&self) -> Option<CodeView> {
+        let (rva, size) = self.debug_directory?;
+        let offset = self.rva_to_offset(rva)?;
+
+        for i in 0..size as usize / DEBUG_DIRECTORY_SIZE {
+            let entry = offset + i * DEBUG_DIRECTORY_SIZE;
+            if read_u32(self.data, entry + 12)? != IMAGE_DEBUG_TYPE_CODEVIEW {
+                continue;
+            }
+
+            let data_size = read_u32(self.data, entry + 16)? as usize;
+            let data_offset = read_u32(self.data, entry + 24)? as usize;
+            let record = self
+                .data
+                .get(data_offset..data_offset.checked_add(data_size)?)?;
+            if !record.starts_with(b"RSDS") {
+                continue;
+            }
+
+            let d4: [u8; 8] = record.get(12..20)?.try_into().ok()?;
+            let path = record.get(24..)?;
+            let len = path.iter().position(|c| *c == 0).unwrap_or(path.len());
+            return Some(CodeView {
+                guid: (
+                    read_u32(record, 4)?,
+                    read_u16(record, 8)?,
+                    read_u16(record, 10)?,
+                    d4,
+                ),
+                age: read_u32(record, 20)?,
+                path: String::from_utf8_lossy(&path[..len]).into_owned(),
+            });
+        }
+
+        None
+    }
+
+    /// Checks that the binary has a COFF symbol table.
+    ///
+    /// MSVC never emits one and stores all symbols in a PDB file instead.
+    pub fn has_symbols(&self) -> bool {
+        self.symbols_count != 0
+    }
+
+    /// Returns symbols from the specified section.
+    ///
+    /// Only functions are used for code sections and only variables for data sections.
+    pub fn symbols(&self, section: &Section) -> Vec<SymbolData> {
+        let mut symbols = Vec::new();
+        let mut i = 0;
+        while i < self.symbols_count {
+            let offset = self.symbols_offset + i * COFF_SYMBOL_SIZE;
+            let raw = match self.data.get(offset..offset + COFF_SYMBOL_SIZE) {
+                Some(v) => v,
+                None => break,
+            };
+
+            let value = read_u32(raw, 8).unwrap_or(0);
+            let section_number = i16::from_le_bytes([raw[12], raw[13]]);
+            let kind = u16::from_le_bytes([raw[14], raw[15]]);
+            let storage_class = raw[16];
+            let aux_count = raw[17] as usize;
+            i += 1 + aux_count;
+
+            if section_number as usize != section.number {
+                continue;
+            }
+
+            let is_function = (kind >> IMAGE_SYM_DTYPE_SHIFT) == IMAGE_SYM_DTYPE_FUNCTION;
+            let is_valid = if section.is_code() {
+                is_function && storage_class == IMAGE_SYM_CLASS_EXTERNAL
+            } else {
+                // Static symbols with auxiliary records are section definitions.
+                !is_function
+                    && (storage_class == IMAGE_SYM_CLASS_EXTERNAL
+                        || (storage_class == IMAGE_SYM_CLASS_STATIC && aux_count == 0))
+            };
+
+            if !is_valid {
+                continue;
+            }
+
+            if let Some(name) = symbol_name(&raw[..8], self.strings_offset, self.data) {
+                if !name.starts_with('.') {
+                    symbols.push((u64::from(section.virtual_address) + u64::from(value), name));
+                }
+            }
+        }
+
+        symbols.sort_by_key(|v| v.0);
+
+        // PE format doesn't store the symbols size,
+        // so we have to use the distance to the next symbol.
+        let end = section.rva_range().end;
+        let mut list = Vec::with_capacity(symbols.len());
+        for (i, (address, name)) in symbols.iter().enumerate() {
+            let next = symbols[i..]
+                .iter()
+                .map(|v| v.0)
+                .find(|a| a != address)
+                .unwrap_or(end);
+
+            list.push(SymbolData {
+                name: SymbolName::demangle(name),
+                address: *address,
+                size: next.saturating_sub(*address),
+            });
+        }
+
+        list
+    }
+}
@@ -0,0 +1,462 @@
+//! Resolves rustflags the same way cargo does.
+//!
+//! Sources in the order of precedence:
+//!
+//! 1. `CARGO_ENCODED_RUSTFLAGS`
+//! 2. `RUSTFLAGS`
+//! 3. `target.<triple>.rustflags` from `--config`, `CARGO_TARGET_<TRIPLE>_RUSTFLAGS`
+//!    and cargo config files, joined with all matching `target.'cfg(...)'.rustflags`
+//! 4. `build.rustflags` from the same sources
+
+use std::{env, fs, path};
+
+use crate::config;
+
+/// Returns flags that cargo would pass to rustc when building for the specified target.
+///
+/// `target_cfg` is the output of `rustc --print cfg` for the target, like `unix`
+/// and `target_os="linux"`. `cfg(...)` tables are ignored when it's not set.
+pub fn resolve(
+    target_triple: &str,
+    config_args: &[String],
+    target_cfg: Option<&[String]>,
+) -> Vec<String> {
+    if let Ok(flags) = env::var("CARGO_ENCODED_RUSTFLAGS") {
+        return flags
+            .split('\x1f')
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+
+    if let Ok(flags) = env::var("RUSTFLAGS") {
+        return flags.split_whitespace().map(str::to_string).collect();
+    }
+
+    let layers = load_layers(target_triple, config_args);
+
+    if let Some(flags) = target_flags(&layers, target_triple, target_cfg.unwrap_or_default()) {
+        return flags;
+    }
+
+    merge_flags(&layers, "build.rustflags").unwrap_or_default()
+}
+
+/// Joins `target.<triple>.rustflags` with all matching `target.'cfg(...)'.rustflags`.
+///
+/// Like cargo, the triple table goes first and `cfg` tables follow in the keys order.
+fn target_flags(
+    layers: &[Layer],
+    target_triple: &str,
+    target_cfg: &[String],
+) -> Option<Vec<String>> {
+    let mut flags = merge_flags(layers, &format!("target.{}.rustflags", target_triple));
+
+    let mut cfg_keys: Vec<&str> = layers
+        .iter()
+        .flatten()
+        .map(|(key, _)| key.as_str())
+        .filter(|key| {
+            key.strip_prefix("target.")
+                .and_then(|key| key.strip_suffix(".rustflags"))
+                .and_then(|expr| expr.strip_prefix("cfg("))
+                .and_then(|expr| expr.strip_suffix(')'))
+                .is_some_and(|expr| cfg_matches(expr, target_cfg))
+        })
+        .collect();
+    cfg_keys.sort_unstable();
+    cfg_keys.dedup();
+
+    for key in cfg_keys {
+        if let Some(list) = merge_flags(layers, key) {
+            flags.get_or_insert_with(Vec::new).extend(list);
+        }
+    }
+
+    flags
+}
+
+/// Evaluates a `cfg(...)` expression, like `all(target_arch = "arm", target_os = "none")`.
+///
+/// Invalid expressions never match.
+fn cfg_matches(expr: &str, target_cfg: &[String]) -> bool {
+    let mut s = expr.trim();
+    match eval_cfg(&mut s, target_cfg) {
+        Some(value) => s.trim().is_empty() && value,
+        None => false,
+    }
+}
+
+/// Evaluates a single predicate and advances `s` past it.
+fn eval_cfg(s: &mut &str, target_cfg: &[String]) -> Option<bool> {
+    let name_len = s
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(s.len());
+    if name_len == 0 {
+        return None;
+    }
+
+    let name = &s[..name_len];
+    *s = s[name_len..].trim_start();
+
+    if let Some(rest) = s.strip_prefix('(') {
+        *s = rest;
+        let mut values = Vec::new();
+        loop {
+            *s = s.trim_start();
+            if let Some(rest) = s.strip_prefix(')') {
+                *s = rest;
+                break;
+            }
+
+            values.push(eval_cfg(s, target_cfg)?);
+            *s = s.trim_start();
+            if let Some(rest) = s.strip_prefix(',') {
+                *s = rest;
+            } else if !s.starts_with(')') {
+                return None;
+            }
+        }
+
+        return match name {
+            "all" => Some(values.iter().all(|v| *v)),
+            "any" => Some(values.iter().any(|v| *v)),
+            "not" if values.len() == 1 => Some(!values[0]),
+            _ => None,
+        };
+    }
+
+    if let Some(rest) = s.strip_prefix('=') {
+        let rest = rest.trim_start().strip_prefix('"')?;
+        let end = rest.find('"')?;
+        let value = &rest[..end];
+        *s = &rest[end + 1..];
+        let item = format!("{}=\"{}\"", name, value);
+        return Some(target_cfg.contains(&item));
+    }
+
+    Some(target_cfg.iter().any(|v| v == name))
+}
+
+/// A flattened config file, like `[("build.rustflags", ...)]`.
+type Layer = Vec<(String, json::JsonValue)>;
+
+/// Returns config layers starting from the lowest precedence.
+fn load_layers(target_triple: &str, config_args: &[String]) -> Vec<Layer> {
+    let mut layers = Vec::new();
+
+    for path in config_files() {
+        if let Ok(text) = fs::read_to_string(&path) {
+            layers.push(parse(&text));
+        }
+    }
+
+    let target_env = format!(
+        "CARGO_TARGET_{}_RUSTFLAGS",
+        target_triple.to_ascii_uppercase().replace(['-', '.'], "_")
+    );
+    let env_keys = [
+        ("CARGO_BUILD_RUSTFLAGS", "build.rustflags".to_string()),
+        (
+            target_env.as_str(),
+            format!("target.{}.rustflags", target_triple),
+        ),
+    ];
+    for (name, key) in env_keys.iter() {
+        if let Ok(value) = env::var(name) {
+            layers.push(vec![(key.clone(), value.into())]);
+        }
+    }
+
+    // `--config` is either a path to a file or a `KEY=VALUE` pair in TOML syntax.
+    // The latter ones take precedence.
+    for value in config_args {
+        let path = path::Path::new(value);
+        if value.ends_with(".toml") && path.is_file() {
+            if let Ok(text) = fs::read_to_string(path) {
+                layers.push(parse(&text));
+            }
+        } else {
+            layers.push(parse(value));
+        }
+    }
+
+    layers
+}
+
+/// Returns cargo config files starting from the lowest precedence.
+///
+/// Files in the current directory and its parents have a higher precedence
+/// than the one in the cargo home directory.
+fn config_files() -> Vec<path::PathBuf> {
+    let find = |dir: &path::Path| {
+        ["config.toml", "config"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    };
+
+    let mut files = Vec::new();
+    if let Ok(dir) = env::current_dir() {
+        for dir in dir.ancestors() {
+            if let Some(path) = find(&dir.join(".cargo")) {
+                files.push(path);
+            }
+        }
+    }
+
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(path::PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| path::Path::new(&home).join(".cargo"))
+        });
+
+    if let Some(path) = cargo_home.and_then(|dir| find(&dir)) {
+        let is_same = |p: &path::PathBuf| fs::canonicalize(p).ok() == fs::canonicalize(&path).ok();
+        if !files.iter().any(is_same) {
+            files.push(path);
+        }
+    }
+
+    files.reverse();
+    files
+}
+
+/// Joins flags from all layers, like cargo does for arrays.
+///
+/// A flag from a layer with a higher precedence is placed later.
+fn merge_flags(layers: &[Layer], key: &str) -> Option<Vec<String>> {
+    let mut flags: Option<Vec<String>> = None;
+    for (_, value) in layers.iter().flatten().filter(|(k, _)| k == key) {
+        let list = flags.get_or_insert_with(Vec::new);
+        if let Some(s) = value.as_str() {
+            list.extend(s.split_whitespace().map(str::to_string));
+        } else {
+            list.extend(
+                value
+                    .members()
+                    .filter_map(|v| v.as_str())
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    flags
+}
+
+/// Parses a TOML file into a list of dotted keys and values.
+///
+/// Only tables, simple values and arrays, including multi-line ones, are supported.
+/// Invalid lines are ignored, since cargo will report them anyway.
+fn parse(text: &str) -> Layer {
+    let mut layer = Vec::new();
+    let mut table = String::new();
+    let mut pending = String::new();
+    for line in text.lines() {
+        let line = config::strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // A key cannot start with `[`, so it's always a table header,
+        // even when a quoted key contains `=`, like `[target.'cfg(unix = "x")']`.
+        if pending.is_empty() && line.starts_with('[') {
+            table = normalize_key(line.trim_start_matches('[').trim_end_matches(']'));
+            continue;
+        }
+
+        pending.push_str(line);
+        pending.push(' ');
+
+        // Wait for the end of a multi-line array.
+        if !is_balanced(&pending) {
+            continue;
+        }
+
+        if let Some((key, value)) = config::split_key_value(&pending) {
+            let key = normalize_key(key);
+            let key = if table.is_empty() {
+                key
+            } else {
+                format!("{}.{}", table, key)
+            };
+            if let Some(value) = config::parse_value(value.trim()) {
+                layer.push((key, value));
+            }
+        }
+
+        pending.clear();
+    }
+
+    layer
+}
+
+/// Removes quotes and spaces from a dotted key, like `target."x86_64-pc-windows-msvc"`.
+///
+/// Dots and spaces inside quotes are preserved.
+fn normalize_key(key: &str) -> String {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quote = None;
+    for c in key.trim().chars() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '.') => parts.push(std::mem::take(&mut part)),
+            (None, c) if c.is_whitespace() => {}
+            _ => part.push(c),
+        }
+    }
+    parts.push(part);
+
+    parts.join(".")
+}
+
+fn is_balanced(s: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quotes = config::Quotes::default();
+    for c in s.chars() {
+        if quotes.is_quoted(c) {
+            continue;
+        }
+
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth <= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(v: &[&str]) -> Option<Vec<String>> {
+        Some(v.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn build_table() {
+        let layer = parse(
+            "[build]\n\
+             rustflags = [\"-C\", \"target-cpu=native\"] # comment\n",
+        );
+        assert_eq!(
+            merge_flags(&[layer], "build.rustflags"),
+            strings(&["-C", "target-cpu=native"])
+        );
+    }
+
+    #[test]
+    fn target_table() {
+        let layers = vec![parse(
+            "[target.x86_64-unknown-linux-gnu]\n\
+             rustflags = [\n\
+                 \"-Zlocation-detail=none\",\n\
+             ]\n\
+             [target.\"thumbv7em-none-eabihf\"]\n\
+             rustflags = \"-C link-arg=-Tlink.x\"\n",
+        )];
+        assert_eq!(
+            merge_flags(&layers, "target.x86_64-unknown-linux-gnu.rustflags"),
+            strings(&["-Zlocation-detail=none"])
+        );
+        assert_eq!(
+            merge_flags(&layers, "target.thumbv7em-none-eabihf.rustflags"),
+            strings(&["-C", "link-arg=-Tlink.x"])
+        );
+    }
+
+    #[test]
+    fn quoted_keys() {
+        assert_eq!(
+            normalize_key(r#"target . "x86_64-pc-windows-msvc" . rustflags"#),
+            "target.x86_64-pc-windows-msvc.rustflags"
+        );
+        assert_eq!(
+            normalize_key(r#"target.'cfg(target_os = "linux")'"#),
+            r#"target.cfg(target_os = "linux")"#
+        );
+
+        // A `--config` value.
+        let layer = parse(r#"target."aarch64-apple-darwin".rustflags=["-Cdebuginfo=1"]"#);
+        assert_eq!(
+            merge_flags(&[layer], "target.aarch64-apple-darwin.rustflags"),
+            strings(&["-Cdebuginfo=1"])
+        );
+
+        // `=` inside of a quoted table name.
+        let layer = parse("[target.'cfg(target_os = \"linux\")']\nrustflags = [\"-Cx\"]\n");
+        assert_eq!(layer.len(), 1);
+        assert_eq!(layer[0].0, r#"target.cfg(target_os = "linux").rustflags"#);
+    }
+
+    #[test]
+    fn precedence() {
+        let layers = vec![
+            parse("build.rustflags = [\"-Ca\"]"),
+            parse("build.rustflags = \"-Cb\""),
+        ];
+        assert_eq!(
+            merge_flags(&layers, "build.rustflags"),
+            strings(&["-Ca", "-Cb"])
+        );
+        assert_eq!(merge_flags(&layers, "target.x.rustflags"), None);
+    }
+
+    #[test]
+    fn cfg_expressions() {
+        let cfg: Vec<String> = ["unix", "target_os=\"linux\"", "target_arch=\"x86_64\""]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert!(cfg_matches("unix", &cfg));
+        assert!(!cfg_matches("windows", &cfg));
+        assert!(cfg_matches(r#"target_os = "linux""#, &cfg));
+        assert!(!cfg_matches(r#"target_os = "none""#, &cfg));
+        assert!(cfg_matches(
+            r#"all(unix, not(target_os = "macos"), any(target_arch = "arm", target_arch = "x86_64"))"#,
+            &cfg
+        ));
+        assert!(cfg_matches("all()", &cfg));
+        assert!(!cfg_matches("any()", &cfg));
+        assert!(!cfg_matches("not(unix, windows)", &cfg));
+        assert!(!cfg_matches("unix)", &cfg));
+        assert!(!cfg_matches("foo(unix)", &cfg));
+    }
+
+    #[test]
+    fn cfg_tables() {
+        let cfg: Vec<String> = ["target_os=\"none\"", "target_arch=\"arm\""]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let layers = vec![
+            parse(
+                "[target.'cfg(all(target_arch = \"arm\", target_os = \"none\"))']\n\
+                 rustflags = [\"-C\", \"link-arg=-Tlink.x\"]\n\
+                 [target.'cfg(unix)']\n\
+                 rustflags = [\"-Cunix\"]\n\
+                 [build]\n\
+                 rustflags = [\"-Cbuild\"]\n",
+            ),
+            parse("target.thumbv7em-none-eabihf.rustflags = [\"-Ctriple\"]"),
+        ];
+
+        assert_eq!(
+            target_flags(&layers, "thumbv7em-none-eabihf", &cfg),
+            strings(&["-Ctriple", "-C", "link-arg=-Tlink.x"])
+        );
+        assert_eq!(
+            target_flags(&layers, "thumbv6m-none-eabi", &cfg),
+            strings(&["-C", "link-arg=-Tlink.x"])
+        );
+        assert_eq!(target_flags(&layers, "thumbv6m-none-eabi", &[]), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Per source file sizes.
+//!
+//! Only PDB files are supported for now, since they contain line tables
+//! for both functions and inline sites.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::{CrateData, Options};
+
+pub struct SourceFile {
+    pub name: String,
+    pub size: u64,
+    /// Bytes of functions inlined from this file into other functions.
+    pub inlined: u64,
+}
+
+/// A code range from a line table.
+struct LineRange {
+    start: u64,
+    end: u64,
+    /// An index in the files list.
+    file: usize,
+    /// 0 for a function itself, 1 for a function inlined into it and so on.
+    depth: u32,
+}
+
+#[derive(Default)]
+pub(crate) struct LineRanges {
+    files: Vec<String>,
+    file_ids: HashMap<String, usize>,
+    ranges: Vec<LineRange>,
+}
+
+impl LineRanges {
+    pub fn push(&mut self, file: &str, range: Range<u64>, depth: u32) {
+        if range.is_empty() {
+            return;
+        }
+
+        let file = match self.file_ids.get(file) {
+            Some(id) => *id,
+            None => {
+                self.files.push(file.to_string());
+                self.file_ids.insert(file.to_string(), self.files.len() - 1);
+                self.files.len() - 1
+            }
+        };
+
+        self.ranges.push(LineRange {
+            start: range.start,
+            end: range.end,
+            file,
+            depth,
+        });
+    }
+
+    /// Computes per file sizes.
+    ///
+    /// A byte covered by multiple ranges belongs to the most deeply inlined one,
+    /// because line tables of a function point to the call site for inlined code.
+    pub fn resolve(self) -> Vec<SourceFile> {
+        let ranges = &self.ranges;
+        let mut events = Vec::with_capacity(ranges.len() * 2);
+        for (i, range) in ranges.iter().enumerate() {
+            events.push((range.start, true, i));
+            events.push((range.end, false, i));
+        }
+
+        // Process range ends first.
+        events.sort_by_key(|v| (v.0, v.1));
+
+        let mut sizes = vec![(0, 0); self.files.len()];
+        let mut active: Vec<usize> = Vec::new();
+        let mut prev = 0;
+        for (pos, is_start, idx) in events {
+            if pos > prev {
+                let top = active.iter().max_by_key(|i| (ranges[**i].depth, **i));
+                if let Some(range) = top.map(|i| &ranges[*i]) {
+                    sizes[range.file].0 += pos - prev;
+                    if range.depth != 0 {
+                        sizes[range.file].1 += pos - prev;
+                    }
+                }
+            }
+
+            prev = pos;
+            if is_start {
+                active.push(idx);
+            } else {
+                active.retain(|i| *i != idx);
+            }
+        }
+
+        self.files
+            .into_iter()
+            .zip(sizes)
+            .filter(|(_, (size, _))| *size != 0)
+            .map(|(name, (size, inlined))| SourceFile {
+                name,
+                size,
+                inlined,
+            })
+            .collect()
+    }
+}
+
+pub struct Sources<'a> {
+    pub filter_out_size: u64,
+    pub filter_out_inlined: u64,
+    pub filter_out_len: usize,
+    pub files: Vec<&'a SourceFile>,
+}
+
+/// Returns the biggest source files.
+pub fn collect<'a>(d: &'a CrateData, options: &Options) -> Sources<'a> {
+    let mut list: Vec<&SourceFile> = d.data.source_files.iter().collect();
+    list.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+
+    let n = if options.n == 0 {
+        list.len()
+    } else {
+        options.n
+    };
+    let rest = &list[n.min(list.len())..];
+    Sources {
+        filter_out_size: rest.iter().map(|f| f.size).sum(),
+        filter_out_inlined: rest.iter().map(|f| f.inlined).sum(),
+        filter_out_len: rest.len(),
+        files: list.iter().take(n).cloned().collect(),
+    }
+}
@@ -0,0 +1,108 @@
+//! Mach-O universal (fat) binaries support.
+//!
+//! A universal binary is just a list of regular Mach-O files, one per architecture.
+
+use std::convert::TryInto;
+
+const FAT_MAGIC: u32 = 0xcafebabe;
+const FAT_MAGIC_64: u32 = 0xcafebabf;
+
+const CPU_ARCH_ABI64: u32 = 0x0100_0000;
+const CPU_ARCH_ABI64_32: u32 = 0x0200_0000;
+const CPU_TYPE_X86: u32 = 7;
+const CPU_TYPE_ARM: u32 = 12;
+const CPU_TYPE_POWERPC: u32 = 18;
+
+const CPU_SUBTYPE_MASK: u32 = 0x00ff_ffff;
+const CPU_SUBTYPE_X86_64_H: u32 = 8;
+const CPU_SUBTYPE_ARM64E: u32 = 2;
+
+pub struct Slice<'a> {
+    pub arch: String,
+    pub data: &'a [u8],
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Returns all architecture slices or `None` when `data` is not a universal binary.
+pub fn parse(data: &[u8]) -> Option<Vec<Slice<'_>>> {
+    let is_64_bit = match read_u32(data, 0)? {
+        FAT_MAGIC => false,
+        FAT_MAGIC_64 => true,
+        _ => return None,
+    };
+
+    // Java class files have the same magic, but their next field is a version,
+    // which is way bigger than any sane number of architectures.
+    let count = read_u32(data, 4)? as usize;
+    if count == 0 || count > 32 {
+        return None;
+    }
+
+    let entry_size = if is_64_bit { 32 } else { 20 };
+    let mut slices = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 8 + i * entry_size;
+        let cpu_type = read_u32(data, entry)?;
+        let cpu_subtype = read_u32(data, entry + 4)?;
+        let (offset, size) = if is_64_bit {
+            (read_u64(data, entry + 8)?, read_u64(data, entry + 16)?)
+        } else {
+            (
+                read_u32(data, entry + 8)?.into(),
+                read_u32(data, entry + 12)?.into(),
+            )
+        };
+
+        let start: usize = offset.try_into().ok()?;
+        let end = start.checked_add(size.try_into().ok()?)?;
+        slices.push(Slice {
+            arch: arch_name(cpu_type, cpu_subtype),
+            data: data.get(start..end)?,
+        });
+    }
+
+    Some(slices)
+}
+
+/// Returns an architecture name the same way `lipo` does.
+fn arch_name(cpu_type: u32, cpu_subtype: u32) -> String {
+    let subtype = cpu_subtype & CPU_SUBTYPE_MASK;
+    let name = match cpu_type {
+        CPU_TYPE_X86 => "i386",
+        t if t == CPU_TYPE_X86 | CPU_ARCH_ABI64 => {
+            if subtype == CPU_SUBTYPE_X86_64_H {
+                "x86_64h"
+            } else {
+                "x86_64"
+            }
+        }
+        CPU_TYPE_ARM => match subtype {
+            9 => "armv7",
+            11 => "armv7s",
+            12 => "armv7k",
+            _ => "arm",
+        },
+        t if t == CPU_TYPE_ARM | CPU_ARCH_ABI64 => {
+            if subtype == CPU_SUBTYPE_ARM64E {
+                "arm64e"
+            } else {
+                "arm64"
+            }
+        }
+        t if t == CPU_TYPE_ARM | CPU_ARCH_ABI64_32 => "arm64_32",
+        CPU_TYPE_POWERPC => "ppc",
+        t if t == CPU_TYPE_POWERPC | CPU_ARCH_ABI64 => "ppc64",
+        _ => return format!("cpu{:#x}", cpu_type),
+    };
+
+    name.to_string()
+}
@@ -0,0 +1,457 @@
+//! A minimal WebAssembly module reader.
+//!
+//! Function sizes are taken from the code section and names from the `name` custom section.
+
+use std::collections::HashMap;
+use std::path;
+
+use binfarce::demangle::{Kind, SymbolData, SymbolName};
+
+use crate::{Data, Error};
+
+const SECTION_CUSTOM: u8 = 0;
+const SECTION_IMPORT: u8 = 2;
+const SECTION_CODE: u8 = 10;
+const SECTION_DATA: u8 = 11;
+
+const IMPORT_FUNCTION: u8 = 0;
+const IMPORT_TABLE: u8 = 1;
+const IMPORT_MEMORY: u8 = 2;
+const IMPORT_GLOBAL: u8 = 3;
+const IMPORT_TAG: u8 = 4;
+
+const NAME_SUBSECTION_FUNCTIONS: u8 = 1;
+
+/// A crate name of data segments.
+const DATA: &str = "[Data]";
+/// A crate name of custom sections, like debug info or the `name` section itself.
+const CUSTOM_SECTIONS: &str = "[Custom sections]";
+
+pub fn is_wasm(data: &[u8]) -> bool {
+    data.starts_with(b"\0asm")
+}
+
+struct Section<'a> {
+    id: u8,
+    name: &'a str, // custom sections only
+    offset: usize,
+    data: &'a [u8],
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, offset: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        let b = *self.data.get(self.offset)?;
+        self.offset += 1;
+        Some(b)
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    /// Reads an unsigned LEB128 value.
+    fn uleb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= u64::from(b & 0x7f) << shift;
+            }
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Skips a signed LEB128 value.
+    fn skip_sleb(&mut self) -> Option<()> {
+        while self.u8()? & 0x80 != 0 {}
+        Some(())
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        self.uleb().map(|v| v as usize)
+    }
+
+    fn name(&mut self) -> Option<&'a str> {
+        let len = self.len()?;
+        std::str::from_utf8(self.bytes(len)?).ok()
+    }
+
+    fn skip_limits(&mut self) -> Option<()> {
+        let flags = self.u8()?;
+        self.uleb()?; // min
+        if flags & 0x01 != 0 {
+            self.uleb()?; // max
+        }
+        Some(())
+    }
+
+    /// Skips a constant expression, like an offset of a data segment.
+    fn skip_const_expr(&mut self) -> Option<()> {
+        loop {
+            match self.u8()? {
+                // end
+                0x0b => return Some(()),
+                // i32.const, i64.const
+                0x41 | 0x42 => self.skip_sleb()?,
+                // global.get
+                0x23 => {
+                    self.uleb()?;
+                }
+                // Extended constant expressions: add, sub and mul.
+                0x6a | 0x6b | 0x6c | 0x7c | 0x7d | 0x7e => {}
+                _ => return None,
+            }
+        }
+    }
+}
+
+fn parse_sections(data: &[u8]) -> Option<Vec<Section<'_>>> {
+    if !is_wasm(data) || data.len() < 8 {
+        return None;
+    }
+
+    let mut s = Reader::new(data);
+    s.offset = 8; // magic and version
+
+    let mut sections = Vec::new();
+    while !s.at_end() {
+        let id = s.u8()?;
+        let len = s.len()?;
+        let offset = s.offset;
+        let body = s.bytes(len)?;
+
+        let (name, body) = if id == SECTION_CUSTOM {
+            let mut r = Reader::new(body);
+            let name = r.name()?;
+            (name, &body[r.offset..])
+        } else {
+            ("", body)
+        };
+
+        sections.push(Section {
+            id,
+            name,
+            offset,
+            data: body,
+        });
+    }
+
+    Some(sections)
+}
+
+/// Returns the sizes of all known and custom sections.
+pub fn section_sizes(data: &[u8]) -> Vec<(String, u64)> {
+    let sections = match parse_sections(data) {
+        Some(v) => v,
+        None => return Vec::new(),
+    };
+
+    let mut list = Vec::new();
+    for section in sections {
+        let name = match section.id {
+            SECTION_CODE => "code",
+            SECTION_DATA => "data",
+            SECTION_CUSTOM => section.name,
+            _ => continue,
+        };
+        list.push((name.to_string(), section.data.len() as u64));
+    }
+
+    list
+}
+
+fn imported_functions_count(data: &[u8]) -> Option<u64> {
+    let mut s = Reader::new(data);
+    let mut count = 0;
+    for _ in 0..s.uleb()? {
+        s.name()?; // module
+        s.name()?; // field
+        match s.u8()? {
+            IMPORT_FUNCTION => {
+                s.uleb()?;
+                count += 1;
+            }
+            IMPORT_TABLE => {
+                s.u8()?; // reference type
+                s.skip_limits()?;
+            }
+            IMPORT_MEMORY => s.skip_limits()?,
+            IMPORT_GLOBAL => {
+                s.u8()?; // value type
+                s.u8()?; // mutability
+            }
+            IMPORT_TAG => {
+                s.u8()?; // attribute
+                s.uleb()?;
+            }
+            _ => return None,
+        }
+    }
+
+    Some(count)
+}
+
+fn function_names(data: &[u8]) -> Option<HashMap<u64, &str>> {
+    let mut names = HashMap::new();
+    let mut s = Reader::new(data);
+    while !s.at_end() {
+        let id = s.u8()?;
+        let len = s.len()?;
+        let body = s.bytes(len)?;
+        if id != NAME_SUBSECTION_FUNCTIONS {
+            continue;
+        }
+
+        let mut s = Reader::new(body);
+        for _ in 0..s.uleb()? {
+            let index = s.uleb()?;
+            let name = s.name()?;
+            names.insert(index, name);
+        }
+    }
+
+    Some(names)
+}
+
+/// Returns data segments as (offset, size) pairs.
+fn data_segments(data: &[u8], section_offset: usize) -> Option<Vec<(u64, u64)>> {
+    let mut s = Reader::new(data);
+    let mut segments = Vec::new();
+    for _ in 0..s.uleb()? {
+        match s.uleb()? {
+            0 => s.skip_const_expr()?,
+            1 => {} // passive
+            2 => {
+                s.uleb()?; // memory index
+                s.skip_const_expr()?;
+            }
+            _ => return None,
+        }
+
+        let len = s.len()?;
+        segments.push(((section_offset + s.offset) as u64, len as u64));
+        s.bytes(len)?;
+    }
+
+    Some(segments)
+}
+
+pub fn collect_data(path: &path::Path, data: &[u8]) -> Result<Data, Error> {
+    let sections =
+        parse_sections(data).ok_or_else(|| Error::UnsupportedFileFormat(path.to_owned()))?;
+
+    let mut imported_functions = 0;
+    let mut names = HashMap::new();
+    for section in &sections {
+        if section.id == SECTION_IMPORT {
+            imported_functions = imported_functions_count(section.data).unwrap_or(0);
+        } else if section.id == SECTION_CUSTOM && section.name == "name" {
+            names = function_names(section.data).unwrap_or_default();
+        }
+    }
+
+    // Unlike native binaries, there is no single section with all the interesting data,
+    // so functions, data segments and custom sections are all listed together.
+    let mut symbols = Vec::new();
+    let mut total_size = 0;
+    for section in &sections {
+        match section.id {
+            SECTION_CODE => {
+                let mut s = Reader::new(section.data);
+                let count = s.uleb().unwrap_or(0);
+                for i in 0..count {
+                    // A function body is prefixed with its size.
+                    let start = s.offset;
+                    if s.len().and_then(|len| s.bytes(len)).is_none() {
+                        break;
+                    }
+
+                    let index = imported_functions + i;
+                    let name = match names.get(&index) {
+                        Some(name) => function_name(name),
+                        None => SymbolName::demangle(&format!("[function #{}]", index)),
+                    };
+
+                    symbols.push(SymbolData {
+                        name,
+                        address: (section.offset + start) as u64,
+                        size: (s.offset - start) as u64,
+                    });
+                }
+            }
+            SECTION_DATA => match data_segments(section.data, section.offset) {
+                Some(segments) => {
+                    for (i, (offset, size)) in segments.into_iter().enumerate() {
+                        symbols.push(SymbolData {
+                            name: pseudo_symbol(format!("[data segment #{}]", i), DATA),
+                            address: offset,
+                            size,
+                        });
+                    }
+                }
+                None => {
+                    symbols.push(SymbolData {
+                        name: pseudo_symbol("[data section]".to_string(), DATA),
+                        address: section.offset as u64,
+                        size: section.data.len() as u64,
+                    });
+                }
+            },
+            SECTION_CUSTOM => {
+                symbols.push(SymbolData {
+                    name: pseudo_symbol(
+                        format!("[custom section {}]", section.name),
+                        CUSTOM_SECTIONS,
+                    ),
+                    address: section.offset as u64,
+                    size: section.data.len() as u64,
+                });
+            }
+            _ => continue,
+        }
+
+        total_size += section.data.len() as u64;
+    }
+
+    Ok(Data {
+        symbols,
+        file_size: 0,
+        text_size: total_size,
+        section_name: Some("code+data+custom".to_string()),
+        sections: Vec::new(),
+        memory: None,
+        symbol_objects: HashMap::new(),
+        source_files: Vec::new(),
+        warnings: Vec::new(),
+        arch: None,
+    })
+}
+
+/// Parses a function name from the `name` section.
+///
+/// wasm-ld stores already demangled names, like `core::fmt::write::h0123456789abcdef`,
+/// so legacy ones are recognized by their hash suffix instead.
+fn function_name(name: &str) -> SymbolName {
+    let sym = SymbolName::demangle(name);
+    if sym.kind != Kind::Unknown {
+        return sym;
+    }
+
+    if let Some(trimmed) = strip_legacy_hash(name) {
+        return SymbolName {
+            complete: name.to_string(),
+            trimmed: trimmed.to_string(),
+            crate_name: None,
+            kind: Kind::Legacy,
+        };
+    }
+
+    sym
+}
+
+/// Strips a `::h` followed by 16 hex digits suffix.
+fn strip_legacy_hash(name: &str) -> Option<&str> {
+    let idx = name.len().checked_sub(19)?;
+    let (path, hash) = (name.get(..idx)?, name.get(idx..)?);
+    let digits = hash.strip_prefix("::h")?;
+    if !path.is_empty() && digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Creates a name of something that is not a function, like a data segment.
+fn pseudo_symbol(name: String, crate_name: &str) -> SymbolName {
+    SymbolName {
+        complete: name.clone(),
+        trimmed: name,
+        crate_name: Some(crate_name.to_string()),
+        kind: Kind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_names() {
+        let sym = function_name("core::fmt::write::h0123456789abcdef");
+        assert!(sym.kind == Kind::Legacy);
+        assert_eq!(sym.trimmed, "core::fmt::write");
+
+        assert!(function_name("core::fmt::write").kind == Kind::Unknown);
+        assert!(function_name("main::h0123456789abcdeg").kind == Kind::Unknown);
+        assert!(function_name("::h0123456789abcdef").kind == Kind::Unknown);
+    }
+
+    #[test]
+    fn leb128() {
+        let mut s = Reader::new(&[0x00, 0x7f, 0x80, 0x01, 0xe5, 0x8e, 0x26]);
+        assert_eq!(s.uleb(), Some(0));
+        assert_eq!(s.uleb(), Some(127));
+        assert_eq!(s.uleb(), Some(128));
+        assert_eq!(s.uleb(), Some(624_485));
+        assert_eq!(s.uleb(), None);
+
+        // Redundant padding bytes are allowed.
+        assert_eq!(Reader::new(&[0x81, 0x80, 0x80, 0x00]).uleb(), Some(1));
+
+        // Unterminated.
+        assert_eq!(Reader::new(&[0x80, 0x80]).uleb(), None);
+
+        let mut s = Reader::new(&[0x7f, 0xc0, 0xbb, 0x78, 0x2a]);
+        assert_eq!(s.skip_sleb(), Some(()));
+        assert_eq!(s.skip_sleb(), Some(()));
+        assert_eq!(s.u8(), Some(0x2a));
+    }
+
+    #[test]
+    fn sections() {
+        let module = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic and version
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // code: one empty function
+            0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0xff, // data: one active segment
+            0x00, 0x03, 0x01, 0x78, 0x00, // custom section `x`
+        ];
+        assert_eq!(
+            section_sizes(&module),
+            [
+                ("code".to_string(), 4),
+                ("data".to_string(), 7),
+                ("x".to_string(), 1)
+            ]
+        );
+
+        let d = collect_data(path::Path::new("x.wasm"), &module).unwrap();
+        let names: Vec<&str> = d.symbols.iter().map(|s| s.name.complete.as_str()).collect();
+        assert_eq!(
+            names,
+            ["[function #0]", "[data segment #0]", "[custom section x]"]
+        );
+        assert_eq!(d.symbols[0].size, 3);
+        assert_eq!(d.symbols[1].size, 1);
+        assert_eq!(d.symbols[1].name.crate_name.as_deref(), Some(DATA));
+    }
+}